@@ -1,20 +1,47 @@
 "use strict";
 
 define(["knockout", "reqwest"], function(ko, reqwest) {
+	const LAST_NAME_PATTERN = /[^>|]*$/;
+	const PAGE_SIZE = 100;
+
 	const Application = function() {
 		this.loading = ko.observable(false);
 		this.selectedConnection = ko.observable(undefined);
 		this.availableConnections = ko.observableArray([]);
 		this.queryRegionHierarchy = ko.observable("");
+		this.suggestions = ko.observableArray([]);
 		this.preferredLanguage = ko.observable("");
+		this.searchLanguages = ko.observable("");
+		this.minDepth = ko.observable("");
+		this.maxDepth = ko.observable("");
+		this.regionTypes = ko.observable("");
+		this.matchMode = ko.observable("substring");
+		this.availableMatchModes = [
+			{ value: "substring", text: "Substring" },
+			{ value: "prefix", text: "Prefix" },
+			{ value: "exact", text: "Exact" },
+			{ value: "glob", text: "Glob (* and ?)" },
+			{ value: "regex", text: "Regular expression" },
+		];
 		this.availableLanguages = ko.observableArray([]);
 		this.showBiggerRegions = ko.observable(true);
+		this.searchAllConnections = ko.observable(false);
+		this.strictMatching = ko.observable(false);
+		this.explainMatches = ko.observable(false);
+		this.groupByRegion = ko.observable(false);
+		this.collapsePaths = ko.observable(false);
+		this.rejectedHierarchies = ko.observableArray([]);
+		this.missingParts = ko.observableArray([]);
+		this.isMultipleConnections = ko.observable(false);
 		this.regionNames = ko.observable({});
 		this.regionHierarchies = ko.observableArray([]);
+		this.drillPath = ko.observableArray([]);
+		this.pageOffset = ko.observable(0);
+		this.totalHierarchies = ko.observable(0);
 		this.errorMessage = ko.observable("");
 
 		this.isConnectionInvalid = ko.pureComputed(function() {
-			return this.selectedConnection() === undefined;
+			return this.selectedConnection() === undefined && !this.searchAllConnections();
 		}, this);
 
 		this.isRegionNameInvalid = ko.pureComputed(function() {
@@ -33,10 +60,22 @@ define(["knockout", "reqwest"], function(ko, reqwest) {
 			return this.regionHierarchies().length > 0;
 		}, this);
 
+		this.isDrillPathPresent = ko.pureComputed(function() {
+			return this.drillPath().length > 0;
+		}, this);
+
 		this.isErrorMessagePresent = ko.pureComputed(function() {
 			return this.errorMessage() !== "";
 		}, this);
 
+		this.isMissingPartsPresent = ko.pureComputed(function() {
+			return this.missingParts().length > 0;
+		}, this);
+
+		this.isRejectedHierarchiesPresent = ko.pureComputed(function() {
+			return this.rejectedHierarchies().length > 0;
+		}, this);
+
 		this.regionHierarchiesFiltered = ko.pureComputed(function() {
 			if (this.showBiggerRegions()) {
 				return this.regionHierarchies();
@@ -45,8 +84,51 @@ define(["knockout", "reqwest"], function(ko, reqwest) {
 			}
 		}, this);
 
+		this.attributeNames = ko.pureComputed(function() {
+			const names = new Set();
+
+			for (const hierarchy of this.regionHierarchies()) {
+				const region = this.hierarchyRegion(hierarchy);
+
+				if (region && region.attributes) {
+					Object.keys(region.attributes).forEach(name => names.add(name));
+				}
+			}
+
+			return Array.from(names).sort();
+		}, this);
+
+		this.isPagingAvailable = ko.pureComputed(function() {
+			return this.totalHierarchies() > PAGE_SIZE;
+		}, this);
+
+		this.hasPreviousPage = ko.pureComputed(function() {
+			return this.pageOffset() > 0;
+		}, this);
+
+		this.hasNextPage = ko.pureComputed(function() {
+			return this.pageOffset() + PAGE_SIZE < this.totalHierarchies();
+		}, this);
+
+		this.pageText = ko.pureComputed(function() {
+			const first = this.pageOffset() + 1;
+			const last = Math.min(this.pageOffset() + PAGE_SIZE, this.totalHierarchies());
+
+			return `${first} - ${last} of ${this.totalHierarchies()}`;
+		}, this);
+
+		this.isSuggestionsPresent = ko.pureComputed(function() {
+			return this.suggestions().length > 0;
+		}, this);
+
+		this.queryRegionHierarchy
+			.extend({ rateLimit: { timeout: 300, method: "notifyWhenChangesStop" } })
+			.subscribe(this.updateSuggestions, this);
+		this.searchAllConnections.subscribe(this.updateSuggestions, this);
+
 		this.updateConnections();
 		this.loadBiggerRegionSettings();
+		this.loadSearchLanguagesSettings();
 	};
 
 	Application.prototype.loadSettings = function(name, callback) {
@@ -73,10 +155,29 @@ define(["knockout", "reqwest"], function(ko, reqwest) {
 		this.loadSettings("preferredLanguage", this.preferredLanguage);
 	};
 
+	Application.prototype.loadSearchLanguagesSettings = function() {
+		this.loadSettings("searchLanguages", this.searchLanguages);
+	};
+
+	Application.prototype.languages = function() {
+		return (this.searchLanguages() || "").split(/[\s,]+/).filter(language => language !== "");
+	};
+
+	Application.prototype.leafFilter = function() {
+		const depth = value => (value === "" || value === undefined ? undefined : parseInt(value, 10));
+
+		return {
+			minDepth: depth(this.minDepth()),
+			maxDepth: depth(this.maxDepth()),
+			types: (this.regionTypes() || "").split(/\s*,\s*/).filter(type => type !== ""),
+		};
+	};
+
 	Application.prototype.saveSettings = function() {
 		const settings = ko.utils.stringifyJson({
 			selectedConnection: this.selectedConnection(),
 			preferredLanguage: this.preferredLanguage(),
+			searchLanguages: this.searchLanguages(),
 			showBiggerRegions: this.showBiggerRegions(),
 		});
 
@@ -112,30 +213,46 @@ define(["knockout", "reqwest"], function(ko, reqwest) {
 			);
 	};
 
-	Application.prototype.searchRegion = function() {
+	Application.prototype.loadHierarchies = function(url, data, callback) {
 		this.loading(true);
 
 		reqwest({
-			url: "/api/v1/find_region",
+			url: url,
 			type: "json",
 			method: "POST",
 			contentType: "application/json",
-			data: JSON.stringify({
-				connection: this.selectedConnection(),
-				query: this.queryRegionHierarchy(),
-			}),
+			data: JSON.stringify(data),
 		})
 			.then(
 				function(resp) {
-					if (resp.success) {
+					if (resp.success && resp.result.connections !== undefined) {
+						this.showConnectionResults(resp.result.connections);
+						this.updateLanguageList();
+
+						if (callback !== undefined) {
+							callback();
+						}
+					} else if (resp.success) {
+						this.isMultipleConnections(false);
 						this.regionNames(resp.result.regions);
 						this.regionHierarchies(resp.result.hierarchies);
+						this.totalHierarchies(resp.result.total || 0);
+						this.missingParts(resp.result.missingParts || []);
+						this.rejectedHierarchies(resp.result.rejected || []);
 						this.errorMessage("");
 						this.updateLanguageList();
+
+						if (callback !== undefined) {
+							callback();
+						}
 					} else {
 						this.regionNames({});
 						this.regionHierarchies([]);
+						this.totalHierarchies(0);
+						this.missingParts([]);
+						this.rejectedHierarchies([]);
 						this.errorMessage(resp.message);
+						this.selectQueryPosition(resp.position);
 					}
 
 					this.loading(false);
@@ -149,6 +266,205 @@ define(["knockout", "reqwest"], function(ko, reqwest) {
 			);
 	};
 
+	Application.prototype.selectQueryPosition = function(position) {
+		if (position === undefined) {
+			return;
+		}
+
+		const input = document.getElementById("region-name");
+
+		input.focus();
+		input.setSelectionRange(position - 1, position);
+	};
+
+	Application.prototype.showConnectionResults = function(connections) {
+		const regionNames = {};
+		const hierarchies = [];
+		const errors = [];
+		const missingParts = new Set();
+		const rejected = [];
+		let total = 0;
+
+		for (const connection of connections) {
+			if (connection.success) {
+				Object.assign(regionNames, connection.result.regions);
+				total = Math.max(total, connection.result.total || 0);
+				(connection.result.missingParts || []).forEach(part => missingParts.add(part));
+				(connection.result.rejected || []).forEach(hierarchy => rejected.push(hierarchy));
+
+				for (const hierarchy of connection.result.hierarchies) {
+					hierarchies.push(
+						Object.assign(
+							{
+								connection: connection.description,
+								connectionIndex: connection.connection,
+								regions: connection.result.regions,
+							},
+							hierarchy
+						)
+					);
+				}
+			} else {
+				errors.push(`${connection.description}: ${connection.message}`);
+			}
+		}
+
+		this.isMultipleConnections(true);
+		this.regionNames(regionNames);
+		this.regionHierarchies(hierarchies);
+		this.totalHierarchies(total);
+		this.missingParts(Array.from(missingParts).sort((a, b) => a - b));
+		this.rejectedHierarchies(rejected);
+		this.errorMessage(errors.join("; "));
+	};
+
+	Application.prototype.searchConnection = function() {
+		if (this.searchAllConnections()) {
+			return this.availableConnections().map(connection => connection.index);
+		} else {
+			return this.selectedConnection();
+		}
+	};
+
+	Application.prototype.searchRegion = function() {
+		this.loadPage(0);
+	};
+
+	Application.prototype.previousPage = function() {
+		this.loadPage(Math.max(this.pageOffset() - PAGE_SIZE, 0));
+	};
+
+	Application.prototype.nextPage = function() {
+		this.loadPage(this.pageOffset() + PAGE_SIZE);
+	};
+
+	Application.prototype.loadPage = function(offset) {
+		this.drillPath([]);
+		this.pageOffset(offset);
+		this.loadHierarchies("/api/v1/find_region", {
+			connection: this.searchConnection(),
+			query: this.queryRegionHierarchy(),
+			matchMode: this.matchMode(),
+			languages: this.languages(),
+			filter: this.leafFilter(),
+			strict: this.strictMatching(),
+			explain: this.explainMatches(),
+			group: this.groupByRegion(),
+			collapse: this.groupByRegion() && this.collapsePaths(),
+			offset: offset,
+			limit: PAGE_SIZE,
+		});
+	};
+
+	Application.prototype.matchExplanation = function(hierarchy) {
+		if (hierarchy.matches === undefined) {
+			return "";
+		}
+
+		return hierarchy.matches
+			.map(match => {
+				const name = match.name !== undefined ? `${match.name} (${match.language})` : "not checked";
+
+				return `Part ${match.part}: ${match.regionId} - ${name}`;
+			})
+			.join("\n");
+	};
+
+	Application.prototype.showChildren = function(hierarchy) {
+		const connection =
+			hierarchy.connectionIndex !== undefined ? hierarchy.connectionIndex : this.selectedConnection();
+		const crumb = {
+			id: hierarchy.regionId,
+			name: this.regionName(hierarchy.regionId, hierarchy.regions),
+			connection: connection,
+		};
+
+		this.loadHierarchies(
+			"/api/v1/children",
+			{
+				connection: connection,
+				id: crumb.id,
+				languages: this.languages(),
+			},
+			function() {
+				this.drillPath.push(crumb);
+			}.bind(this)
+		);
+	};
+
+	Application.prototype.showCrumbChildren = function(crumb) {
+		const path = this.drillPath();
+		const index = path.indexOf(crumb);
+
+		this.loadHierarchies(
+			"/api/v1/children",
+			{
+				connection: crumb.connection,
+				id: crumb.id,
+				languages: this.languages(),
+			},
+			function() {
+				this.drillPath(path.slice(0, index + 1));
+			}.bind(this)
+		);
+	};
+
+	Application.prototype.updateSuggestions = function() {
+		const query = this.queryRegionHierarchy();
+		const index = query.search(LAST_NAME_PATTERN);
+		const lastPart = query.substring(index).trim();
+		// Empty name after separator suggests children of parent regions.
+		const isChildrenSuggested = lastPart === "" && query.charAt(index - 1) === ">";
+
+		// Suggestions are loaded from one connection, so they are disabled when all connections
+		// are searched.
+		if (
+			this.isConnectionInvalid() ||
+			this.searchAllConnections() ||
+			(lastPart === "" && !isChildrenSuggested)
+		) {
+			this.suggestions([]);
+
+			return;
+		}
+
+		reqwest({
+			url: "/api/v1/suggest",
+			type: "json",
+			method: "POST",
+			contentType: "application/json",
+			data: JSON.stringify({
+				connection: this.selectedConnection(),
+				query: query,
+				languages: this.languages(),
+				matchMode: this.matchMode(),
+			}),
+		})
+			.then(
+				function(resp) {
+					if (resp.success && query === this.queryRegionHierarchy()) {
+						this.suggestions(resp.result);
+					} else {
+						this.suggestions([]);
+					}
+				}.bind(this)
+			)
+			.fail(
+				function() {
+					this.suggestions([]);
+				}.bind(this)
+			);
+	};
+
+	Application.prototype.applySuggestion = function(suggestion) {
+		const query = this.queryRegionHierarchy();
+		const index = query.search(LAST_NAME_PATTERN);
+		const separator = index > 0 ? " " : "";
+
+		this.queryRegionHierarchy(query.substring(0, index) + separator + suggestion.name);
+		this.suggestions([]);
+	};
+
 	Application.prototype.updateLanguageList = function() {
 		const languages = new Set();
 
@@ -162,23 +478,47 @@ define(["knockout", "reqwest"], function(ko, reqwest) {
 		this.loadLanguageSettings();
 	};
 
-	Application.prototype.namedHierarhy = function(hierarchy) {
-		const partNames = [];
-		const regionNames = this.regionNames();
+	Application.prototype.regionName = function(regionId, regions) {
+		const regionNames = regions || this.regionNames();
 		const preferredLanguage = this.preferredLanguage();
 
-		for (const partId of hierarchy.parts) {
-			if (partId in regionNames) {
-				const region = regionNames[partId];
-				const name = region.names[preferredLanguage] || region.defaultName;
+		if (regionId in regionNames) {
+			const region = regionNames[regionId];
 
-				partNames.push(name);
-			} else {
-				partNames.push(`<${partId}>`);
-			}
+			return region.names[preferredLanguage] || region.defaultName;
+		} else {
+			return `<${regionId}>`;
+		}
+	};
+
+	Application.prototype.hierarchyRegion = function(hierarchy) {
+		const regionNames = hierarchy.regions || this.regionNames();
+
+		return regionNames[hierarchy.regionId];
+	};
+
+	Application.prototype.regionAttribute = function(hierarchy, name) {
+		const region = this.hierarchyRegion(hierarchy);
+
+		if (region && region.attributes && name in region.attributes) {
+			return region.attributes[name];
+		} else {
+			return "";
 		}
+	};
+
+	Application.prototype.namedHierarhy = function(hierarchy) {
+		return hierarchy.parts.map(partId => this.regionName(partId, hierarchy.regions)).join(" > ");
+	};
+
+	Application.prototype.alternatesCount = function(hierarchy) {
+		return (hierarchy.alternates || []).length + (hierarchy.collapsed || 0);
+	};
 
-		return partNames.join(" > ");
+	Application.prototype.alternatesText = function(hierarchy) {
+		return (hierarchy.alternates || [])
+			.map(alternate => this.namedHierarhy(Object.assign({ regions: hierarchy.regions }, alternate)))
+			.join("\n");
 	};
 
 	Application.prototype.areaCode = function(hierarchy) {
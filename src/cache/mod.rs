@@ -0,0 +1,443 @@
+use crate::config::Config;
+use crate::config::ConnectionSettings;
+use crate::database::Hierarchy;
+use crate::database::Region;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::mem;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+const BYTES_IN_MEGABYTE: u64 = 1024 * 1024;
+
+/// Approximate memory used by hash map entry and key besides stored strings.
+const ENTRY_OVERHEAD: usize = 128;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum CacheKey {
+    RegionsByName {
+        connection: String,
+        languages: String,
+        name: String,
+    },
+    RegionById {
+        connection: String,
+        languages: String,
+        id: i64,
+    },
+    HierarchyById {
+        connection: String,
+        id: i64,
+    },
+}
+
+impl CacheKey {
+    fn connection(&self) -> &str {
+        match self {
+            CacheKey::RegionsByName { connection, .. } => connection,
+            CacheKey::RegionById { connection, .. } => connection,
+            CacheKey::HierarchyById { connection, .. } => connection,
+        }
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            CacheKey::RegionsByName {
+                connection,
+                languages,
+                name,
+            } => connection.len() + languages.len() + name.len(),
+            CacheKey::RegionById {
+                connection,
+                languages,
+                ..
+            } => connection.len() + languages.len(),
+            CacheKey::HierarchyById { connection, .. } => connection.len(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum CacheValue {
+    Regions(HashMap<i64, Region>),
+    Region(Option<Region>),
+    Hierarchies(Vec<Hierarchy>),
+}
+
+impl CacheValue {
+    fn size(&self) -> usize {
+        match self {
+            CacheValue::Regions(regions) => regions.values().map(region_size).sum(),
+            CacheValue::Region(Some(region)) => region_size(region),
+            CacheValue::Region(None) => 0,
+            CacheValue::Hierarchies(hierarchies) => hierarchies.iter().map(hierarchy_size).sum(),
+        }
+    }
+}
+
+fn region_size(region: &Region) -> usize {
+    // Every name is stored twice: as is and in lower case name set.
+    mem::size_of::<Region>()
+        + region.default_name().len()
+        + region
+            .names()
+            .iter()
+            .map(|name| 2 * name.name().len() + name.language().len() + ENTRY_OVERHEAD)
+            .sum::<usize>()
+        + region
+            .attributes()
+            .iter()
+            .map(|(name, value)| name.len() + value.size() + ENTRY_OVERHEAD)
+            .sum::<usize>()
+}
+
+fn hierarchy_size(hierarchy: &Hierarchy) -> usize {
+    mem::size_of::<Hierarchy>() + mem::size_of_val(hierarchy.parts())
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheStatistics {
+    enabled: bool,
+    hits: u64,
+    misses: u64,
+    entries: usize,
+    memory: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct RegionCacheRef {
+    inner: Option<Arc<Mutex<RegionCache>>>,
+}
+
+impl RegionCacheRef {
+    fn with_cache<F, T>(&self, callback: F) -> Option<T>
+    where
+        F: FnOnce(&mut RegionCache) -> T,
+    {
+        match self.inner.as_ref().map(|inner| inner.lock()) {
+            Some(Ok(ref mut cache)) => Some(callback(cache)),
+            Some(Err(err)) => {
+                warn!("Failed to acquire cache lock - {}", err);
+
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub fn get(&self, key: &CacheKey) -> Option<CacheValue> {
+        self.with_cache(|cache| cache.get(key)).unwrap_or(None)
+    }
+
+    pub fn insert(&self, key: CacheKey, value: CacheValue) {
+        self.with_cache(|cache| cache.insert(key, value));
+    }
+
+    pub fn invalidate(&self, connection: &str) {
+        self.with_cache(|cache| cache.invalidate(connection));
+    }
+
+    pub fn statistics(&self) -> CacheStatistics {
+        self.with_cache(|cache| cache.statistics())
+            .unwrap_or(CacheStatistics {
+                enabled: false,
+                hits: 0,
+                misses: 0,
+                entries: 0,
+                memory: 0,
+            })
+    }
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    value: CacheValue,
+    size: usize,
+    created: Instant,
+    tick: u64,
+}
+
+/// Least recently used cache. Entries are evicted when total approximate size of entries exceeds
+/// memory limit or when entry is older than time to live.
+#[derive(Debug)]
+struct RegionCache {
+    ttl: Duration,
+    memory_limit: usize,
+    memory: usize,
+    tick: u64,
+    hits: u64,
+    misses: u64,
+    entries: HashMap<CacheKey, CacheEntry>,
+    usage: BTreeMap<u64, CacheKey>,
+}
+
+impl RegionCache {
+    fn new(ttl: Duration, memory_limit: usize) -> RegionCache {
+        RegionCache {
+            ttl,
+            memory_limit,
+            memory: 0,
+            tick: 0,
+            hits: 0,
+            misses: 0,
+            entries: HashMap::new(),
+            usage: BTreeMap::new(),
+        }
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    fn get(&mut self, key: &CacheKey) -> Option<CacheValue> {
+        let tick = self.next_tick();
+        let ttl = self.ttl;
+
+        match self.entries.get_mut(key) {
+            Some(entry) if entry.created.elapsed() < ttl => {
+                self.usage.remove(&entry.tick);
+                self.usage.insert(tick, key.clone());
+                entry.tick = tick;
+                self.hits += 1;
+
+                Some(entry.value.clone())
+            }
+            Some(_) => {
+                self.remove(key);
+                self.misses += 1;
+
+                None
+            }
+            None => {
+                self.misses += 1;
+
+                None
+            }
+        }
+    }
+
+    fn insert(&mut self, key: CacheKey, value: CacheValue) {
+        let size = key.size() + value.size() + ENTRY_OVERHEAD;
+
+        if size > self.memory_limit {
+            debug!("Cache value is too big: key = {:?}, size = {}", key, size);
+
+            return;
+        }
+
+        self.remove(&key);
+
+        while self.memory + size > self.memory_limit {
+            match self.usage.keys().next().cloned() {
+                Some(tick) => {
+                    if let Some(key) = self.usage.remove(&tick) {
+                        self.remove(&key);
+                    }
+                }
+                None => break,
+            }
+        }
+
+        let tick = self.next_tick();
+
+        self.memory += size;
+        self.usage.insert(tick, key.clone());
+        self.entries.insert(
+            key,
+            CacheEntry {
+                value,
+                size,
+                created: Instant::now(),
+                tick,
+            },
+        );
+    }
+
+    fn remove(&mut self, key: &CacheKey) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.memory -= entry.size;
+            self.usage.remove(&entry.tick);
+        }
+    }
+
+    fn invalidate(&mut self, connection: &str) {
+        debug!("Invalidate cache: connection = {}", connection);
+
+        let keys: Vec<_> = self
+            .entries
+            .keys()
+            .filter(|key| key.connection() == connection)
+            .cloned()
+            .collect();
+
+        for key in keys {
+            self.remove(&key);
+        }
+    }
+
+    fn statistics(&self) -> CacheStatistics {
+        CacheStatistics {
+            enabled: true,
+            hits: self.hits,
+            misses: self.misses,
+            entries: self.entries.len(),
+            memory: self.memory,
+        }
+    }
+}
+
+/// Returns string which identifies connection and its query schema in cache keys.
+pub fn connection_key(connection: &ConnectionSettings) -> String {
+    format!(
+        "{}@{}:{}/{}#{}",
+        connection.role(),
+        connection.host(),
+        connection
+            .port()
+            .map(|port| port.to_string())
+            .unwrap_or_default(),
+        connection.database(),
+        connection.query_schema(),
+    )
+}
+
+pub fn region_cache(config: &Config) -> RegionCacheRef {
+    let inner = config.cache().map(|settings| {
+        let ttl = Duration::from_secs(settings.ttl());
+        let memory_limit = (settings.memory_limit() * BYTES_IN_MEGABYTE) as usize;
+
+        Arc::new(Mutex::new(RegionCache::new(ttl, memory_limit)))
+    });
+
+    RegionCacheRef { inner }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Size of hierarchy entry with empty value and single character connection.
+    const ENTRY_SIZE: usize = 1 + ENTRY_OVERHEAD;
+
+    fn hierarchy_key(connection: &str, id: i64) -> CacheKey {
+        CacheKey::HierarchyById {
+            connection: connection.into(),
+            id,
+        }
+    }
+
+    fn regions_key(connection: &str, languages: &str) -> CacheKey {
+        CacheKey::RegionsByName {
+            connection: connection.into(),
+            languages: languages.into(),
+            name: "%london%".into(),
+        }
+    }
+
+    fn cache(entries: usize) -> RegionCache {
+        let mut cache = RegionCache::new(Duration::from_secs(60), entries * ENTRY_SIZE);
+
+        for id in 1..=entries as i64 {
+            cache.insert(hierarchy_key("a", id), CacheValue::Hierarchies(Vec::new()));
+        }
+
+        cache
+    }
+
+    /// Returns connection settings with given field replaced.
+    fn connection(field: &str, value: &str) -> ConnectionSettings {
+        let yaml: String = [
+            ("description", "Test"),
+            ("query_schema", "PG"),
+            ("host", "localhost"),
+            ("port", "5432"),
+            ("database", "regions"),
+            ("role", "reader"),
+            ("password", "secret"),
+        ]
+        .iter()
+        .map(|&(name, default)| {
+            let value = if name == field { value } else { default };
+
+            format!("{}: {}\n", name, value)
+        })
+        .collect();
+
+        serde_yaml::from_str(&yaml).unwrap()
+    }
+
+    #[test]
+    fn evict_least_recently_inserted_entry() {
+        let mut cache = cache(2);
+
+        cache.insert(hierarchy_key("a", 3), CacheValue::Hierarchies(Vec::new()));
+
+        assert!(cache.get(&hierarchy_key("a", 1)).is_none());
+        assert!(cache.get(&hierarchy_key("a", 2)).is_some());
+        assert!(cache.get(&hierarchy_key("a", 3)).is_some());
+        assert_eq!(cache.memory, 2 * ENTRY_SIZE);
+    }
+
+    #[test]
+    fn refresh_entry_on_get() {
+        let mut cache = cache(2);
+
+        assert!(cache.get(&hierarchy_key("a", 1)).is_some());
+
+        cache.insert(hierarchy_key("a", 3), CacheValue::Hierarchies(Vec::new()));
+
+        assert!(cache.get(&hierarchy_key("a", 1)).is_some());
+        assert!(cache.get(&hierarchy_key("a", 2)).is_none());
+        assert_eq!(cache.statistics().hits, 2);
+        assert_eq!(cache.statistics().misses, 1);
+    }
+
+    #[test]
+    fn separate_entries_by_languages_and_connection() {
+        let mut cache = RegionCache::new(Duration::from_secs(60), 100 * ENTRY_SIZE);
+
+        cache.insert(regions_key("a", "en"), CacheValue::Regions(HashMap::new()));
+        cache.insert(hierarchy_key("a", 1), CacheValue::Hierarchies(Vec::new()));
+
+        assert!(cache.get(&regions_key("a", "en")).is_some());
+        assert!(cache.get(&regions_key("a", "en,fr")).is_none());
+        assert!(cache.get(&regions_key("b", "en")).is_none());
+        assert!(cache.get(&hierarchy_key("b", 1)).is_none());
+
+        cache.insert(hierarchy_key("b", 1), CacheValue::Hierarchies(Vec::new()));
+        cache.invalidate("a");
+
+        assert!(cache.get(&hierarchy_key("a", 1)).is_none());
+        assert!(cache.get(&hierarchy_key("b", 1)).is_some());
+    }
+
+    #[test]
+    fn identify_connection_by_server_and_query_schema() {
+        let key = connection_key(&connection("", ""));
+
+        assert_eq!(connection_key(&connection("password", "other")), key);
+        assert_eq!(connection_key(&connection("description", "Other")), key);
+
+        let changes = [
+            ("role", "writer"),
+            ("host", "remote"),
+            ("port", "5433"),
+            ("database", "other"),
+            ("query_schema", "LITE"),
+        ];
+        let mut keys: Vec<_> = changes
+            .iter()
+            .map(|&(field, value)| connection_key(&connection(field, value)))
+            .chain(Some(key))
+            .collect();
+
+        keys.sort();
+        keys.dedup();
+
+        assert_eq!(keys.len(), changes.len() + 1);
+    }
+}
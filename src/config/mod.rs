@@ -16,6 +16,7 @@ pub type ConfigRef = Arc<Config>;
 pub struct Config {
     connections: ConnectionsSettings,
     query_schemas: HashMap<String, QuerySchemaSettings>,
+    cache: Option<CacheSettings>,
 }
 
 impl Config {
@@ -26,6 +27,26 @@ impl Config {
     pub fn query_schemas(&self) -> &HashMap<String, QuerySchemaSettings> {
         &self.query_schemas
     }
+
+    pub fn cache(&self) -> Option<&CacheSettings> {
+        self.cache.as_ref()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CacheSettings {
+    ttl: u64,
+    memory_limit: u64,
+}
+
+impl CacheSettings {
+    pub fn ttl(&self) -> u64 {
+        self.ttl
+    }
+
+    pub fn memory_limit(&self) -> u64 {
+        self.memory_limit
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -60,15 +81,35 @@ impl DynamicConnectionsSettings {
     }
 }
 
-#[derive(Debug, Clone, Deserialize)]
+/// Database engine of connection, query schemas are written in SQL dialect of the same engine.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConnectionKind {
+    #[default]
+    Postgres,
+    Sqlite,
+    File,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
 pub struct ConnectionSettings {
     description: String,
     query_schema: String,
+    #[serde(default)]
+    kind: ConnectionKind,
+    #[serde(default)]
     host: String,
     port: Option<u16>,
+    #[serde(default)]
     database: String,
+    #[serde(default)]
     role: String,
     password: Option<String>,
+    names: Option<String>,
+    hierarchies: Option<String>,
+    preload: Option<PreloadSettings>,
+    statement_timeout: Option<u64>,
+    max_results: Option<usize>,
 }
 
 impl ConnectionSettings {
@@ -80,6 +121,10 @@ impl ConnectionSettings {
         &self.query_schema
     }
 
+    pub fn kind(&self) -> ConnectionKind {
+        self.kind
+    }
+
     pub fn host(&self) -> &str {
         &self.host
     }
@@ -88,6 +133,7 @@ impl ConnectionSettings {
         self.port
     }
 
+    /// Returns database name, or path to database file for SQLite connection.
     pub fn database(&self) -> &str {
         &self.database
     }
@@ -99,13 +145,63 @@ impl ConnectionSettings {
     pub fn password(&self) -> Option<&String> {
         self.password.as_ref()
     }
+
+    /// Returns path to region names file of file connection.
+    pub fn names(&self) -> Option<&str> {
+        self.names.as_deref()
+    }
+
+    /// Returns path to hierarchies file of file connection.
+    pub fn hierarchies(&self) -> Option<&str> {
+        self.hierarchies.as_deref()
+    }
+
+    pub fn preload(&self) -> Option<&PreloadSettings> {
+        self.preload.as_ref()
+    }
+
+    /// Returns statement timeout in milliseconds, overrides query schema timeout.
+    pub fn statement_timeout(&self) -> Option<u64> {
+        self.statement_timeout
+    }
+
+    /// Returns maximal number of hierarchies returned by single search.
+    pub fn max_results(&self) -> Option<usize> {
+        self.max_results
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct PreloadSettings {
+    interval: u64,
+}
+
+impl PreloadSettings {
+    pub fn interval(&self) -> u64 {
+        self.interval
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct QuerySchemaSettings {
+    #[serde(default)]
     regions_by_id: String,
+    #[serde(default)]
     regions_by_name: String,
+    #[serde(default)]
     hierarchy_by_id: String,
+    regions_by_point: Option<String>,
+    children_by_id: Option<String>,
+    regions_by_prefix: Option<String>,
+    regions_by_regex: Option<String>,
+    all_hierarchies: Option<String>,
+    all_regions: Option<String>,
+    statement_timeout: Option<u64>,
+    type_attribute: Option<String>,
+    #[serde(default)]
+    language_priority: Vec<String>,
+    #[serde(default)]
+    dialect: ConnectionKind,
 }
 
 impl QuerySchemaSettings {
@@ -120,6 +216,74 @@ impl QuerySchemaSettings {
     pub fn hierarchy_by_id(&self) -> &str {
         &self.hierarchy_by_id
     }
+
+    pub fn regions_by_point(&self) -> Option<&str> {
+        self.regions_by_point.as_deref()
+    }
+
+    pub fn children_by_id(&self) -> Option<&str> {
+        self.children_by_id.as_deref()
+    }
+
+    pub fn regions_by_prefix(&self) -> Option<&str> {
+        self.regions_by_prefix.as_deref()
+    }
+
+    pub fn regions_by_regex(&self) -> Option<&str> {
+        self.regions_by_regex.as_deref()
+    }
+
+    pub fn all_hierarchies(&self) -> Option<&str> {
+        self.all_hierarchies.as_deref()
+    }
+
+    pub fn all_regions(&self) -> Option<&str> {
+        self.all_regions.as_deref()
+    }
+
+    /// Returns statement timeout in milliseconds.
+    pub fn statement_timeout(&self) -> Option<u64> {
+        self.statement_timeout
+    }
+
+    /// Returns name of region attribute containing region type.
+    pub fn type_attribute(&self) -> Option<&str> {
+        self.type_attribute.as_deref()
+    }
+
+    /// Returns kind of connections which can execute queries of this schema.
+    pub fn dialect(&self) -> ConnectionKind {
+        self.dialect
+    }
+
+    /// Returns names and texts of all defined queries.
+    pub fn queries(&self) -> Vec<(&'static str, &str)> {
+        let optional_queries = [
+            ("regions_by_point", self.regions_by_point()),
+            ("children_by_id", self.children_by_id()),
+            ("regions_by_prefix", self.regions_by_prefix()),
+            ("regions_by_regex", self.regions_by_regex()),
+            ("all_hierarchies", self.all_hierarchies()),
+            ("all_regions", self.all_regions()),
+        ];
+        let mut result = vec![
+            ("regions_by_id", self.regions_by_id()),
+            ("regions_by_name", self.regions_by_name()),
+            ("hierarchy_by_id", self.hierarchy_by_id()),
+        ];
+
+        for (name, query) in optional_queries {
+            if let Some(query) = query {
+                result.push((name, query));
+            }
+        }
+
+        result
+    }
+
+    pub fn language_priority(&self) -> &[String] {
+        &self.language_priority
+    }
 }
 
 pub fn load<P>(path: P) -> ConfigResult<ConfigRef>
@@ -144,10 +308,16 @@ pub fn connection_settings(
     ConnectionSettings {
         description: description.into(),
         query_schema: query_schema.into(),
+        kind: ConnectionKind::Postgres,
         host: host.into(),
         port,
         database: database.into(),
         role: role.into(),
         password: password.cloned(),
+        names: None,
+        hierarchies: None,
+        preload: None,
+        statement_timeout: None,
+        max_results: None,
     }
 }
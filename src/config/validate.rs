@@ -1,8 +1,15 @@
 use super::Config;
 use super::ConfigError;
 use super::ConfigResult;
+use super::ConnectionKind;
 use super::ConnectionSettings;
+use crate::database;
+use std::iter::Peekable;
 use std::path::Path;
+use std::str::Chars;
+
+/// Keywords of statements allowed in query schemas, all of them only read data.
+const READ_KEYWORDS: &[&str] = &["select", "values", "with"];
 
 #[allow(clippy::needless_pass_by_value)]
 pub fn validate(config: &Config) -> ConfigResult<()> {
@@ -21,6 +28,55 @@ pub fn validate(config: &Config) -> ConfigResult<()> {
         )));
     }
 
+    for (name, query_schema) in query_schemas {
+        for (query_name, query) in query_schema.queries() {
+            let query_name = format!("{}.{}", name, query_name);
+
+            if query_schema.dialect() != ConnectionKind::File {
+                validate_not_empty(query, &query_name)?;
+            }
+
+            validate_query(query, &query_name)?;
+        }
+
+        if let Some(timeout) = query_schema.statement_timeout() {
+            validate_number(timeout, &format!("{}.statement_timeout", name))?;
+        }
+    }
+
+    for connection in config.connections().static_connections() {
+        validate_connection(
+            connection,
+            query_schemas[connection.query_schema()].dialect(),
+        )?;
+
+        if let Some(timeout) = connection.statement_timeout() {
+            validate_number(timeout, "statement_timeout")?;
+        }
+
+        if let Some(max_results) = connection.max_results() {
+            validate_number(max_results as u64, "max_results")?;
+        }
+
+        if let Some(preload) = connection.preload() {
+            let query_schema = &query_schemas[connection.query_schema()];
+
+            validate_number(preload.interval(), "preload.interval")?;
+
+            // File connections are loaded from dataset files, reload does not use queries.
+            if connection.kind() == ConnectionKind::Postgres
+                && (query_schema.all_regions().is_none()
+                    || query_schema.all_hierarchies().is_none())
+            {
+                return Err(ConfigError::format(format_args!(
+                    "Query schema {} must define all_regions and all_hierarchies to preload connection {}",
+                    connection.query_schema(),
+                    connection.description(),
+                )));
+            }
+        }
+    }
+
     if let Some(dynamic_connections) = config.connections().dynamic_connections() {
         let interval = dynamic_connections.interval();
         let command = dynamic_connections.command();
@@ -29,9 +85,88 @@ pub fn validate(config: &Config) -> ConfigResult<()> {
         validate_file(command, "dynamic_connections.command")?;
     }
 
+    if let Some(cache) = config.cache() {
+        validate_number(cache.ttl(), "cache.ttl")?;
+        validate_number(cache.memory_limit(), "cache.memory_limit")?;
+    }
+
+    Ok(())
+}
+
+/// Checks that connection is able to execute queries of its query schema and has all settings
+/// required by its kind.
+fn validate_connection(
+    connection: &ConnectionSettings,
+    dialect: ConnectionKind,
+) -> ConfigResult<()> {
+    if connection.kind() != dialect {
+        return Err(ConfigError::format(format_args!(
+            "Connection {} of kind {:?} can not use query schema {} of dialect {:?}",
+            connection.description(),
+            connection.kind(),
+            connection.query_schema(),
+            dialect,
+        )));
+    }
+
+    match connection.kind() {
+        ConnectionKind::Postgres => {
+            validate_not_empty(connection.host(), "host")?;
+            validate_not_empty(connection.database(), "database")?;
+            validate_not_empty(connection.role(), "role")
+        }
+        ConnectionKind::Sqlite => {
+            validate_not_empty(connection.database(), "database")?;
+            validate_file(connection.database(), "database")?;
+
+            if connection.preload().is_some() {
+                return Err(ConfigError::format(format_args!(
+                    "Connection {} can not be preloaded, preload is supported for postgres and file connections only",
+                    connection.description(),
+                )));
+            }
+
+            Ok(())
+        }
+        ConnectionKind::File => {
+            validate_dataset_file(connection.names(), "names")?;
+            validate_dataset_file(connection.hierarchies(), "hierarchies")
+        }
+    }
+}
+
+/// Checks that dataset file of file connection is given, exists and has known format.
+fn validate_dataset_file(path: Option<&str>, name: &str) -> ConfigResult<()> {
+    let path = path.ok_or_else(|| {
+        ConfigError::format(format_args!(
+            "Value `{}` must be given for file connection",
+            name
+        ))
+    })?;
+
+    validate_file(path, name)?;
+
+    if database::FileFormat::from_path(path).is_none() {
+        return Err(ConfigError::format(format_args!(
+            "{} file ({}) must have csv or jsonl extension",
+            name, path
+        )));
+    }
+
     Ok(())
 }
 
+fn validate_not_empty(value: &str, name: &str) -> ConfigResult<()> {
+    if value.is_empty() {
+        Err(ConfigError::format(format_args!(
+            "Value `{}` must not be empty",
+            name
+        )))
+    } else {
+        Ok(())
+    }
+}
+
 fn validate_number(value: u64, name: &str) -> ConfigResult<()> {
     if value > 0 {
         Ok(())
@@ -43,6 +178,265 @@ fn validate_number(value: u64, name: &str) -> ConfigResult<()> {
     }
 }
 
+/// Checks that query contains single statement which only reads data: `select`, `values` or `with`
+/// statement. Common table expressions and main statement after them are checked the same way,
+/// `select into` is rejected. String literals, quoted identifiers and comments are ignored.
+fn validate_query(query: &str, name: &str) -> ConfigResult<()> {
+    let text = strip_sql(query);
+    let statements = text
+        .split(';')
+        .filter(|statement| !statement.trim().is_empty())
+        .count();
+
+    if statements > 1 {
+        return Err(ConfigError::format(format_args!(
+            "Query `{}` must contain single statement, but {} statements given",
+            name, statements
+        )));
+    }
+
+    if let Some(keyword) = find_write_keyword(&tokenize(&text)) {
+        return Err(ConfigError::format(format_args!(
+            "Query `{}` must be read only, but contains `{}`",
+            name, keyword
+        )));
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Token {
+    Word(String),
+    Open,
+    Close,
+    Comma,
+    Symbol(char),
+}
+
+impl Token {
+    fn text(&self) -> String {
+        match self {
+            Token::Word(word) => word.clone(),
+            Token::Open => "(".into(),
+            Token::Close => ")".into(),
+            Token::Comma => ",".into(),
+            Token::Symbol(ch) => ch.to_string(),
+        }
+    }
+}
+
+/// Splits query without literals and comments to lower case words and punctuation.
+fn tokenize(text: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        let token = match ch {
+            '(' => Token::Open,
+            ')' => Token::Close,
+            ',' => Token::Comma,
+            ';' => continue,
+            _ if ch.is_alphanumeric() || ch == '_' => {
+                let mut word: String = ch.to_lowercase().collect();
+
+                while let Some(&ch) = chars.peek() {
+                    if !ch.is_alphanumeric() && ch != '_' {
+                        break;
+                    }
+
+                    word.extend(ch.to_lowercase());
+                    chars.next();
+                }
+
+                Token::Word(word)
+            }
+            _ if ch.is_whitespace() => continue,
+            _ => Token::Symbol(ch),
+        };
+
+        tokens.push(token);
+    }
+
+    tokens
+}
+
+/// Returns position of parenthesis closing the one opened at `open`, or tokens length if it is
+/// not closed.
+fn closing(tokens: &[Token], open: usize) -> usize {
+    let mut depth = 0;
+
+    for (index, token) in tokens.iter().enumerate().skip(open) {
+        match token {
+            Token::Open => depth += 1,
+            Token::Close if depth == 1 => return index,
+            Token::Close => depth -= 1,
+            _ => {}
+        }
+    }
+
+    tokens.len()
+}
+
+/// Returns keyword which makes statement modify data: leading keyword which is not allowed or
+/// `into` of `select into` statement.
+fn find_write_keyword(tokens: &[Token]) -> Option<String> {
+    match tokens.first() {
+        None => None,
+        Some(Token::Word(word)) if word == "with" => find_cte_write_keyword(&tokens[1..]),
+        Some(Token::Word(word)) if READ_KEYWORDS.contains(&word.as_str()) => {
+            find_top_level_into(&tokens[1..])
+        }
+        Some(Token::Word(word)) => Some(word.clone()),
+        Some(Token::Open) => {
+            let close = closing(tokens, 0);
+
+            find_write_keyword(&tokens[1..close])
+                .or_else(|| find_top_level_into(tokens.get(close + 1..).unwrap_or(&[])))
+        }
+        Some(token) => Some(token.text()),
+    }
+}
+
+/// Checks common table expressions following `with` keyword and the main statement after them.
+fn find_cte_write_keyword(mut tokens: &[Token]) -> Option<String> {
+    if tokens.first() == Some(&Token::Word("recursive".into())) {
+        tokens = &tokens[1..];
+    }
+
+    loop {
+        // Expression name is followed by optional column list, `as`, optional `[not] materialized`
+        // and parenthesized statement.
+        let mut open = 1;
+
+        if tokens.get(open) == Some(&Token::Open) {
+            open = closing(tokens, open) + 1;
+        }
+
+        while let Some(Token::Word(_)) = tokens.get(open) {
+            open += 1;
+        }
+
+        if tokens.get(open) != Some(&Token::Open) {
+            return Some("with".into());
+        }
+
+        let close = closing(tokens, open);
+
+        if let Some(keyword) = find_write_keyword(&tokens[open + 1..close]) {
+            return Some(keyword);
+        }
+
+        tokens = tokens.get(close + 1..).unwrap_or(&[]);
+
+        match tokens.first() {
+            Some(Token::Comma) => tokens = &tokens[1..],
+            _ => break,
+        }
+    }
+
+    if tokens.is_empty() {
+        Some("with".into())
+    } else {
+        find_write_keyword(tokens)
+    }
+}
+
+/// Returns `into` if it is found outside of parentheses.
+fn find_top_level_into(tokens: &[Token]) -> Option<String> {
+    let mut depth = 0;
+
+    for token in tokens {
+        match token {
+            Token::Open => depth += 1,
+            Token::Close => depth -= 1,
+            Token::Word(word) if depth == 0 && word == "into" => return Some(word.clone()),
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Replaces string literals, quoted identifiers and comments with spaces.
+fn strip_sql(query: &str) -> String {
+    let mut result = String::with_capacity(query.len());
+    let mut chars = query.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        match (ch, chars.peek()) {
+            ('\'', _) | ('"', _) => skip_quoted(&mut chars, ch),
+            ('-', Some('-')) => {
+                for ch in chars.by_ref() {
+                    if ch == '\n' {
+                        break;
+                    }
+                }
+            }
+            ('/', Some('*')) => {
+                chars.next();
+
+                let mut previous = ' ';
+
+                for ch in chars.by_ref() {
+                    if previous == '*' && ch == '/' {
+                        break;
+                    }
+
+                    previous = ch;
+                }
+            }
+            ('$', Some(&next)) if next == '$' || next.is_alphabetic() || next == '_' => {
+                skip_dollar_quoted(&mut chars)
+            }
+            _ => {
+                result.push(ch);
+
+                continue;
+            }
+        }
+
+        result.push(' ');
+    }
+
+    result
+}
+
+fn skip_quoted(chars: &mut Peekable<Chars>, quote: char) {
+    while let Some(ch) = chars.next() {
+        if ch == quote {
+            // Doubled quote is escaped quote inside literal.
+            if chars.peek() == Some(&quote) {
+                chars.next();
+            } else {
+                break;
+            }
+        }
+    }
+}
+
+fn skip_dollar_quoted(chars: &mut Peekable<Chars>) {
+    let mut tag = String::from("$");
+
+    for ch in chars.by_ref() {
+        tag.push(ch);
+
+        if ch == '$' {
+            break;
+        }
+    }
+
+    let mut body = String::new();
+
+    for ch in chars.by_ref() {
+        body.push(ch);
+
+        if body.ends_with(&tag) {
+            break;
+        }
+    }
+}
+
 fn validate_file<P>(path: P, name: &str) -> ConfigResult<()>
 where
     P: AsRef<Path>,
@@ -65,3 +459,61 @@ where
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_valid(query: &str) -> bool {
+        validate_query(query, "test").is_ok()
+    }
+
+    #[test]
+    fn allow_keywords_as_identifiers() {
+        assert!(is_valid(
+            "select id, update, created from regions where copy = $1"
+        ));
+        assert!(is_valid(
+            "select r.drop, call_count from regions r for update"
+        ));
+        assert!(is_valid("select * from (select id from regions) as t;"));
+        assert!(is_valid(
+            "select 'delete' as \"insert\" -- drop table\n from regions"
+        ));
+    }
+
+    #[test]
+    fn reject_modifying_statements() {
+        assert!(!is_valid("delete from regions"));
+        assert!(!is_valid("  Update regions set name = $1"));
+        assert!(!is_valid(
+            "with d as (delete from regions returning id) select * from d"
+        ));
+        assert!(!is_valid("select 1; drop table regions"));
+        assert!(!is_valid(
+            "with x as (select 1) insert into regions select * from x"
+        ));
+        assert!(!is_valid("with x as (select 1) delete from regions"));
+        assert!(!is_valid("select * into copy_regions from regions"));
+        assert!(!is_valid("refresh materialized view regions_mv"));
+        assert!(!is_valid("comment on table regions is 'x'"));
+        assert!(!is_valid("cluster regions"));
+    }
+
+    #[test]
+    fn allow_read_statements() {
+        assert!(is_valid(""));
+        assert!(is_valid("values (1, 'en', 'London', true)"));
+        assert!(is_valid(
+            "(select id from regions) union (select id from other)"
+        ));
+        assert!(is_valid(
+            "with recursive parents(id, parent_id) as not materialized (select id, parent_id \
+             from regions where id = any($1)), names as (select * from region_names) \
+             select p.id, n.name from parents p join names n on n.region_id = p.id"
+        ));
+        assert!(is_valid(
+            "select id from regions where id in (select region_id from json_each(?1))"
+        ));
+    }
+}
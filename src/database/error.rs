@@ -1,4 +1,7 @@
+use postgres::error::SqlState;
 use postgres::Error as PgError;
+use rusqlite::Error as SqliteError;
+use rusqlite::ErrorCode as SqliteErrorCode;
 use std::error::Error;
 use std::fmt::Display;
 use std::fmt::Formatter;
@@ -7,10 +10,15 @@ use std::fmt::Result as FmtResult;
 pub type DatabaseResult<T> = Result<T, DatabaseError>;
 
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum DatabaseError {
     ConnectionError { message: String },
     QueryExecutionError { message: String },
     ValueError { message: String },
+    QueryNotDefinedError { message: String },
+    QueryTimeoutError { message: String },
+    MissingColumnError { message: String },
+    FileError { message: String },
 }
 
 impl DatabaseError {
@@ -25,6 +33,12 @@ impl DatabaseError {
     pub fn query_execution_error(error: PgError) -> DatabaseError {
         debug!("Query execution error - {}", error);
 
+        if error.code() == Some(&SqlState::QUERY_CANCELED) {
+            return DatabaseError::QueryTimeoutError {
+                message: "Query execution timed out".into(),
+            };
+        }
+
         DatabaseError::QueryExecutionError {
             message: format!("{}", error),
         }
@@ -37,6 +51,87 @@ impl DatabaseError {
             message: format!("{}", error),
         }
     }
+
+    pub fn sqlite_connection_error(error: SqliteError) -> DatabaseError {
+        debug!("SQLite connection error - {}", error);
+
+        DatabaseError::ConnectionError {
+            message: format!("{}", error),
+        }
+    }
+
+    pub fn sqlite_query_execution_error(error: SqliteError) -> DatabaseError {
+        debug!("SQLite query execution error - {}", error);
+
+        if error.sqlite_error_code() == Some(SqliteErrorCode::OperationInterrupted) {
+            return DatabaseError::QueryTimeoutError {
+                message: "Query execution timed out".into(),
+            };
+        }
+
+        DatabaseError::QueryExecutionError {
+            message: format!("{}", error),
+        }
+    }
+
+    pub fn sqlite_value_error(name: &str, expected: &str) -> DatabaseError {
+        debug!("SQLite value error - {}", name);
+
+        DatabaseError::ValueError {
+            message: format!("Column `{}` must contain {} value", name, expected),
+        }
+    }
+
+    pub fn file_error(path: &str, error: &dyn Error) -> DatabaseError {
+        debug!("File error - {}: {}", path, error);
+
+        DatabaseError::FileError {
+            message: format!("Failed to read file {} - {}", path, error),
+        }
+    }
+
+    pub fn file_format_error(path: &str) -> DatabaseError {
+        debug!("File format error - {}", path);
+
+        DatabaseError::FileError {
+            message: format!("Unknown format of file {}", path),
+        }
+    }
+
+    pub fn file_value_error(path: &str, line: usize, name: &str, expected: &str) -> DatabaseError {
+        debug!("File value error - {}:{}: {}", path, line, name);
+
+        DatabaseError::ValueError {
+            message: format!(
+                "Field `{}` at {}:{} must contain {} value",
+                name, path, line, expected
+            ),
+        }
+    }
+
+    pub fn pattern_error(error: regex::Error) -> DatabaseError {
+        debug!("Pattern error - {}", error);
+
+        DatabaseError::QueryExecutionError {
+            message: format!("{}", error),
+        }
+    }
+
+    pub fn missing_column_error(name: &str) -> DatabaseError {
+        debug!("Missing column error - {}", name);
+
+        DatabaseError::MissingColumnError {
+            message: format!("Column `{}` is missing in query result", name),
+        }
+    }
+
+    pub fn query_not_defined_error(name: &str) -> DatabaseError {
+        debug!("Query not defined error - {}", name);
+
+        DatabaseError::QueryNotDefinedError {
+            message: format!("Query `{}` is not defined in query schema", name),
+        }
+    }
 }
 
 impl Error for DatabaseError {}
@@ -47,6 +142,10 @@ impl Display for DatabaseError {
             DatabaseError::ConnectionError { message } => write!(f, "{}", message),
             DatabaseError::QueryExecutionError { message } => write!(f, "{}", message),
             DatabaseError::ValueError { message } => write!(f, "{}", message),
+            DatabaseError::QueryNotDefinedError { message } => write!(f, "{}", message),
+            DatabaseError::QueryTimeoutError { message } => write!(f, "{}", message),
+            DatabaseError::MissingColumnError { message } => write!(f, "{}", message),
+            DatabaseError::FileError { message } => write!(f, "{}", message),
         }
     }
 }
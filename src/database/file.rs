@@ -0,0 +1,267 @@
+use crate::config::ConnectionSettings;
+use crate::database::AttributeValue;
+use crate::database::DatabaseError;
+use crate::database::DatabaseResult;
+use crate::database::Hierarchy;
+use crate::database::RegionRecord;
+use crate::database::REGION_COLUMNS;
+use serde_json::Map;
+use serde_json::Value as JsonValue;
+use std::fs::File;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::path::Path;
+
+/// Format of dataset file, selected by file extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileFormat {
+    /// Comma separated values with header row.
+    Csv,
+    /// Single JSON object per line.
+    JsonLines,
+}
+
+impl FileFormat {
+    pub fn from_path<P>(path: P) -> Option<FileFormat>
+    where
+        P: AsRef<Path>,
+    {
+        let extension = path.as_ref().extension()?.to_str()?.to_lowercase();
+
+        match extension.as_str() {
+            "csv" => Some(FileFormat::Csv),
+            "jsonl" | "ndjson" => Some(FileFormat::JsonLines),
+            _ => None,
+        }
+    }
+}
+
+/// Loads region names and hierarchies of file connection. Files contain the same columns as
+/// results of `regions_by_name` and `hierarchy_by_id` queries.
+pub fn load_dataset(
+    settings: &ConnectionSettings,
+) -> DatabaseResult<(Vec<RegionRecord>, Vec<Hierarchy>)> {
+    let names = settings.names().unwrap_or_default();
+    let hierarchies = settings.hierarchies().unwrap_or_default();
+    let records = read_rows(names)?
+        .iter()
+        .map(FileRow::region_record)
+        .collect::<DatabaseResult<Vec<_>>>()?;
+    let hierarchies = read_rows(hierarchies)?
+        .iter()
+        .map(FileRow::hierarchy)
+        .collect::<DatabaseResult<Vec<_>>>()?;
+
+    Ok((records, hierarchies))
+}
+
+fn read_rows(path: &str) -> DatabaseResult<Vec<FileRow>> {
+    match FileFormat::from_path(path) {
+        Some(FileFormat::Csv) => read_csv(path),
+        Some(FileFormat::JsonLines) => read_json_lines(path),
+        None => Err(DatabaseError::file_format_error(path)),
+    }
+}
+
+fn read_csv(path: &str) -> DatabaseResult<Vec<FileRow>> {
+    let mut reader =
+        csv::Reader::from_path(path).map_err(|error| DatabaseError::file_error(path, &error))?;
+    let headers: Vec<String> = reader
+        .headers()
+        .map_err(|error| DatabaseError::file_error(path, &error))?
+        .iter()
+        .map(|header| header.trim().to_string())
+        .collect();
+    let mut rows = Vec::new();
+
+    for (index, record) in reader.records().enumerate() {
+        let record = record.map_err(|error| DatabaseError::file_error(path, &error))?;
+
+        rows.push(FileRow {
+            path: path.into(),
+            // Header is the first line.
+            line: index + 2,
+            fields: headers
+                .iter()
+                .cloned()
+                .zip(record.iter().map(|value| FieldValue::Text(value.into())))
+                .collect(),
+        });
+    }
+
+    Ok(rows)
+}
+
+fn read_json_lines(path: &str) -> DatabaseResult<Vec<FileRow>> {
+    let file = File::open(path).map_err(|error| DatabaseError::file_error(path, &error))?;
+    let mut rows = Vec::new();
+
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(|error| DatabaseError::file_error(path, &error))?;
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let object: Map<String, JsonValue> =
+            serde_json::from_str(&line).map_err(|error| DatabaseError::file_error(path, &error))?;
+
+        rows.push(FileRow {
+            path: path.into(),
+            line: index + 1,
+            fields: object
+                .into_iter()
+                .map(|(name, value)| (name, FieldValue::Json(value)))
+                .collect(),
+        });
+    }
+
+    Ok(rows)
+}
+
+/// Field of dataset file row. CSV fields are always text, their type is determined by column.
+#[derive(Debug)]
+enum FieldValue {
+    Text(String),
+    Json(JsonValue),
+}
+
+/// Single row of dataset file with its location used in error messages.
+#[derive(Debug)]
+struct FileRow {
+    path: String,
+    line: usize,
+    fields: Vec<(String, FieldValue)>,
+}
+
+impl FileRow {
+    fn field(&self, name: &str) -> DatabaseResult<&FieldValue> {
+        self.fields
+            .iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, value)| value)
+            .ok_or_else(|| DatabaseError::missing_column_error(name))
+    }
+
+    fn value_error(&self, name: &str, expected: &str) -> DatabaseError {
+        DatabaseError::file_value_error(&self.path, self.line, name, expected)
+    }
+
+    fn integer(&self, name: &str) -> DatabaseResult<Option<i64>> {
+        match self.field(name)? {
+            FieldValue::Text(text) if text.trim().is_empty() => Ok(None),
+            FieldValue::Text(text) => text
+                .trim()
+                .parse()
+                .map(Some)
+                .map_err(|_| self.value_error(name, "integer")),
+            FieldValue::Json(JsonValue::Null) => Ok(None),
+            FieldValue::Json(value) => value
+                .as_i64()
+                .map(Some)
+                .ok_or_else(|| self.value_error(name, "integer")),
+        }
+    }
+
+    fn required_integer(&self, name: &str) -> DatabaseResult<i64> {
+        self.integer(name)?
+            .ok_or_else(|| self.value_error(name, "integer"))
+    }
+
+    fn text(&self, name: &str) -> DatabaseResult<String> {
+        match self.field(name)? {
+            FieldValue::Text(text) => Ok(text.clone()),
+            FieldValue::Json(JsonValue::String(text)) => Ok(text.clone()),
+            FieldValue::Json(_) => Err(self.value_error(name, "text")),
+        }
+    }
+
+    fn boolean(&self, name: &str) -> DatabaseResult<bool> {
+        match self.field(name)? {
+            FieldValue::Text(text) => match text.trim().to_lowercase().as_str() {
+                "true" | "t" | "1" => Ok(true),
+                "false" | "f" | "0" | "" => Ok(false),
+                _ => Err(self.value_error(name, "boolean")),
+            },
+            FieldValue::Json(JsonValue::Bool(value)) => Ok(*value),
+            FieldValue::Json(JsonValue::Null) => Ok(false),
+            FieldValue::Json(_) => Err(self.value_error(name, "boolean")),
+        }
+    }
+
+    fn region_record(&self) -> DatabaseResult<RegionRecord> {
+        let attributes = self
+            .fields
+            .iter()
+            .filter(|(name, _)| !REGION_COLUMNS.contains(&name.as_str()))
+            .filter_map(|(name, value)| {
+                attribute_value(name, value).map(|value| (name.clone(), value))
+            })
+            .collect();
+
+        Ok(RegionRecord::new(
+            self.required_integer("id")?,
+            &self.text("language_code")?,
+            &self.text("name")?,
+            self.boolean("is_default")?,
+            attributes,
+        ))
+    }
+
+    /// Reads hierarchy, `level_N` fields may be omitted or empty for missing levels.
+    fn hierarchy(&self) -> DatabaseResult<Hierarchy> {
+        let mut levels = Vec::new();
+
+        for (name, _) in &self.fields {
+            let level = name
+                .strip_prefix("level_")
+                .and_then(|level| level.parse::<usize>().ok())
+                .filter(|&level| level > 0);
+
+            if let Some(level) = level {
+                if levels.len() < level {
+                    levels.resize(level, None);
+                }
+
+                levels[level - 1] = self.integer(name)?;
+            }
+        }
+
+        if levels.is_empty() {
+            return Err(DatabaseError::missing_column_error("level_1"));
+        }
+
+        Ok(Hierarchy::new(
+            self.required_integer("id")?,
+            self.required_integer("region_id")?,
+            &levels,
+        ))
+    }
+}
+
+/// Converts extra field value. Type of CSV field is guessed from its text, empty fields and
+/// nested JSON values are skipped.
+fn attribute_value(name: &str, value: &FieldValue) -> Option<AttributeValue> {
+    match value {
+        FieldValue::Text(text) if text.is_empty() => None,
+        FieldValue::Text(text) => Some(
+            text.parse()
+                .map(AttributeValue::Integer)
+                .or_else(|_| text.parse().map(AttributeValue::Float))
+                .or_else(|_| text.parse().map(AttributeValue::Boolean))
+                .unwrap_or_else(|_| AttributeValue::Text(text.clone())),
+        ),
+        FieldValue::Json(JsonValue::Null) => None,
+        FieldValue::Json(JsonValue::Bool(value)) => Some(AttributeValue::Boolean(*value)),
+        FieldValue::Json(JsonValue::Number(number)) => number
+            .as_i64()
+            .map(AttributeValue::Integer)
+            .or_else(|| number.as_f64().map(AttributeValue::Float)),
+        FieldValue::Json(JsonValue::String(text)) => Some(AttributeValue::Text(text.clone())),
+        FieldValue::Json(_) => {
+            warn!("Field `{}` contains nested value, it is skipped", name);
+
+            None
+        }
+    }
+}
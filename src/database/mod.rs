@@ -1,40 +1,100 @@
 mod error;
+mod file;
+mod pool;
+mod source;
+mod sqlite;
 
 pub use self::error::DatabaseError;
 pub use self::error::DatabaseResult;
+pub use self::file::load_dataset;
+pub use self::file::FileFormat;
+pub use self::pool::client_pool;
+pub use self::pool::ClientPoolRef;
+pub use self::source::RegionSource;
+pub use self::sqlite::SqliteClient;
 
+use crate::cache;
+use crate::cache::CacheKey;
+use crate::cache::CacheValue;
+use crate::cache::RegionCacheRef;
 use crate::config::ConnectionSettings;
 use crate::config::QuerySchemaSettings;
+use crate::database::pool::PoolGuard;
+use crate::database::pool::PooledConnection;
 use postgres::config::Config;
+use postgres::error::SqlState;
 use postgres::row::Row;
-use postgres::Client;
+use postgres::types::FromSql;
+use postgres::types::ToSql;
+use postgres::types::Type;
+use postgres::CancelToken;
 use postgres::NoTls;
+use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::sync::mpsc;
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::mpsc::Sender;
+use std::thread;
+use std::thread::JoinHandle;
 use std::time::Duration;
 
 #[derive(Debug)]
 pub struct Database<'a> {
     settings: ConnectionSettings,
     query_schema: &'a QuerySchemaSettings,
+    cache: RegionCacheRef,
+    pool: ClientPoolRef,
 }
 
 const DEFAULT_PORT: u16 = 5432;
 
+/// Time given to server to cancel query by itself before watchdog cancels it.
+const WATCHDOG_DELAY: Duration = Duration::from_secs(1);
+
 impl<'a> Database<'a> {
-    pub fn new(settings: ConnectionSettings, query_schema: &QuerySchemaSettings) -> Database {
+    pub fn new(
+        settings: ConnectionSettings,
+        query_schema: &QuerySchemaSettings,
+        cache: RegionCacheRef,
+        pool: ClientPoolRef,
+    ) -> Database<'_> {
         Database {
             settings,
             query_schema,
+            cache,
+            pool,
         }
     }
 
+    /// Returns client using idle pooled connection if there is any, otherwise opens new one.
     pub fn connect(self) -> DatabaseResult<DatabaseClient<'a>> {
+        let connection_key = cache::connection_key(&self.settings);
+        let connection = match self.pool.acquire(&connection_key) {
+            Some(connection) => connection,
+            None => self
+                .pool
+                .guard(&connection_key, PooledConnection::new(self.open()?)),
+        };
+        let statement_timeout = self
+            .settings
+            .statement_timeout()
+            .or_else(|| self.query_schema.statement_timeout())
+            .map(Duration::from_millis);
+        let mut client =
+            DatabaseClient::new(self.query_schema, connection, self.cache, connection_key);
+
+        client.set_statement_timeout(statement_timeout);
+
+        Ok(client)
+    }
+
+    fn open(&self) -> DatabaseResult<postgres::Client> {
         let mut config = Config::new();
-        config.host(&self.settings.host());
+        config.host(self.settings.host());
         config.port(self.settings.port().unwrap_or(DEFAULT_PORT));
-        config.dbname(&self.settings.database());
-        config.user(&self.settings.role());
+        config.dbname(self.settings.database());
+        config.user(self.settings.role());
 
         if let Some(ref password) = self.settings.password() {
             config.password(password);
@@ -42,119 +102,686 @@ impl<'a> Database<'a> {
 
         config.connect_timeout(Duration::from_secs(10));
 
-        let client = config
+        config
             .connect(NoTls)
-            .map_err(DatabaseError::connection_error)?;
-
-        Ok(DatabaseClient::new(self.query_schema, client))
+            .map_err(DatabaseError::connection_error)
     }
 }
 
 pub struct DatabaseClient<'a> {
     query_schema: &'a QuerySchemaSettings,
-    client: Client,
+    connection: PoolGuard,
+    filter: LanguageFilter,
+    cache: RegionCacheRef,
+    connection_key: String,
+    language_key: String,
+    statement_timeout: Option<Duration>,
 }
 
 impl<'a> DatabaseClient<'a> {
-    fn new(query_schema: &QuerySchemaSettings, client: Client) -> DatabaseClient {
+    fn new(
+        query_schema: &QuerySchemaSettings,
+        connection: PoolGuard,
+        cache: RegionCacheRef,
+        connection_key: String,
+    ) -> DatabaseClient<'_> {
         DatabaseClient {
             query_schema,
-            client,
+            connection,
+            filter: LanguageFilter::new(&[], query_schema),
+            cache,
+            connection_key,
+            language_key: String::new(),
+            statement_timeout: None,
         }
     }
 
+    pub fn has_region_types(&self) -> bool {
+        self.filter.has_region_types()
+    }
+
+    /// Sets maximal execution time of every query, `None` disables timeout.
+    pub fn set_statement_timeout(&mut self, statement_timeout: Option<Duration>) {
+        self.statement_timeout = statement_timeout;
+    }
+
+    /// Executes query in read only transaction. If statement timeout is set, query is canceled by
+    /// server when timeout expires. Watchdog cancels query if server does not respond in time.
+    fn query(&mut self, query: &str, params: &[&(dyn ToSql + Sync)]) -> DatabaseResult<Vec<Row>> {
+        let _watchdog = self.watchdog();
+
+        self.execute(query, params, false)
+            .map_err(DatabaseError::query_execution_error)
+    }
+
+    /// Executes query like `query`, statement is prepared once per connection and reused. Cached
+    /// statement is prepared again if it became invalid after database schema change.
+    fn query_prepared(
+        &mut self,
+        query: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> DatabaseResult<Vec<Row>> {
+        let _watchdog = self.watchdog();
+
+        match self.execute(query, params, true) {
+            Err(ref err) if is_stale_statement(err) => {
+                debug!("Prepare statement again after error - {}", err);
+
+                self.connection.remove_statement(query);
+                self.execute(query, params, true)
+            }
+            result => result,
+        }
+        .map_err(DatabaseError::query_execution_error)
+    }
+
+    fn watchdog(&mut self) -> Option<Watchdog> {
+        let cancel_token = self.connection.client().cancel_token();
+
+        self.statement_timeout
+            .map(|timeout| Watchdog::start(cancel_token, timeout + WATCHDOG_DELAY))
+    }
+
+    fn execute(
+        &mut self,
+        query: &str,
+        params: &[&(dyn ToSql + Sync)],
+        prepare: bool,
+    ) -> Result<Vec<Row>, postgres::Error> {
+        let statement = match self.connection.statement(query) {
+            Some(statement) => Some(statement),
+            None if prepare => {
+                let statement = self.connection.client().prepare(query)?;
+
+                self.connection.insert_statement(query, statement.clone());
+
+                Some(statement)
+            }
+            None => None,
+        };
+        let statement_timeout = self.statement_timeout;
+        let mut transaction = self
+            .connection
+            .client()
+            .build_transaction()
+            .read_only(true)
+            .start()?;
+
+        if let Some(timeout) = statement_timeout {
+            transaction.batch_execute(&format!(
+                "set local statement_timeout = {}",
+                timeout.as_millis()
+            ))?;
+        }
+
+        let rows = match statement {
+            Some(ref statement) => transaction.query(statement, params)?,
+            None => transaction.query(query, params)?,
+        };
+
+        transaction.commit()?;
+
+        Ok(rows)
+    }
+
+    /// Restricts region names to given languages. Requested languages are preferred over query
+    /// schema language priority when region has no default name. Empty list allows all languages.
+    pub fn set_languages(&mut self, languages: &[String]) {
+        self.filter = LanguageFilter::new(languages, self.query_schema);
+        // Requested languages define both name filter and priority, so they identify cached
+        // regions together with connection.
+        self.language_key = languages.join(",");
+    }
+
     pub fn regions_by_id<I>(&mut self, it: I) -> DatabaseResult<HashMap<i64, Region>>
     where
         I: IntoIterator<Item = i64>,
     {
-        let ids: Vec<i64> = it.into_iter().collect();
+        let mut result = HashMap::new();
+        let mut ids: Vec<i64> = Vec::new();
+
+        for id in it {
+            match self.cache.get(&self.region_key(id)) {
+                Some(CacheValue::Region(Some(region))) => {
+                    result.insert(id, region);
+                }
+                Some(CacheValue::Region(None)) => {}
+                _ => ids.push(id),
+            }
+        }
+
+        if ids.is_empty() {
+            return Ok(result);
+        }
+
+        let regions = self.uncached_regions_by_id(&ids)?;
+
+        for id in ids {
+            let region = regions.get(&id).cloned();
+
+            self.cache
+                .insert(self.region_key(id), CacheValue::Region(region));
+        }
 
+        result.extend(regions);
+
+        Ok(result)
+    }
+
+    /// Queries regions by identifiers without region cache. Used by bulk lookups, which would
+    /// evict cached regions of searches.
+    pub fn uncached_regions_by_id(&mut self, ids: &[i64]) -> DatabaseResult<HashMap<i64, Region>> {
         debug!("Get region names by id: ids = {:?}", ids);
 
-        let rows = self
-            .client
-            .query(self.query_schema.regions_by_id(), &[&ids])
-            .map_err(DatabaseError::query_execution_error)?;
+        let rows = self.query_prepared(self.query_schema.regions_by_id(), &[&ids])?;
 
         self.collect_regions(rows)
     }
 
     pub fn regions_by_name(&mut self, name: &str) -> DatabaseResult<HashMap<i64, Region>> {
+        let key = CacheKey::RegionsByName {
+            connection: self.connection_key.clone(),
+            languages: self.language_key.clone(),
+            name: name.into(),
+        };
+
+        if let Some(CacheValue::Regions(regions)) = self.cache.get(&key) {
+            return Ok(regions);
+        }
+
         debug!("Get regions by name: name = {}", name);
 
-        let rows = self
-            .client
-            .query(self.query_schema.regions_by_name(), &[&name])
-            .map_err(DatabaseError::query_execution_error)?;
+        let rows = self.query_prepared(self.query_schema.regions_by_name(), &[&name])?;
+        let regions = self.collect_regions(rows)?;
+
+        self.cache.insert(key, CacheValue::Regions(regions.clone()));
+
+        Ok(regions)
+    }
+
+    pub fn regions_by_prefix(&mut self, prefix: &str) -> DatabaseResult<HashMap<i64, Region>> {
+        debug!("Get regions by prefix: prefix = {}", prefix);
+
+        let query = self
+            .query_schema
+            .regions_by_prefix()
+            .ok_or_else(|| DatabaseError::query_not_defined_error("regions_by_prefix"))?;
+        let rows = self.query(query, &[&prefix])?;
 
         self.collect_regions(rows)
     }
 
-    #[inline]
-    fn collect_regions(&self, result: Vec<Row>) -> DatabaseResult<HashMap<i64, Region>> {
-        let mut builders = HashMap::new();
+    pub fn regions_by_regex(&mut self, pattern: &str) -> DatabaseResult<HashMap<i64, Region>> {
+        debug!("Get regions by regex: pattern = {}", pattern);
 
-        for row in result {
-            let id: i64 = row.try_get(0).map_err(DatabaseError::value_error)?;
-            let language: String = row.try_get(1).map_err(DatabaseError::value_error)?;
-            let name: String = row.try_get(2).map_err(DatabaseError::value_error)?;
-            let is_default: bool = row.try_get(3).map_err(DatabaseError::value_error)?;
-            let builder = builders.entry(id).or_insert_with(RegionBuilder::new);
+        let query = self
+            .query_schema
+            .regions_by_regex()
+            .ok_or_else(|| DatabaseError::query_not_defined_error("regions_by_regex"))?;
+        let rows = self.query(query, &[&pattern])?;
 
-            builder.insert_name(language, name, is_default);
-        }
+        self.collect_regions(rows)
+    }
 
-        Ok(builders
-            .into_iter()
-            .map(|(id, builder)| (id, builder.build()))
-            .collect())
+    pub fn regions_by_point(
+        &mut self,
+        latitude: f64,
+        longitude: f64,
+    ) -> DatabaseResult<HashMap<i64, Region>> {
+        debug!(
+            "Get regions by point: latitude = {}, longitude = {}",
+            latitude, longitude
+        );
+
+        let query = self
+            .query_schema
+            .regions_by_point()
+            .ok_or_else(|| DatabaseError::query_not_defined_error("regions_by_point"))?;
+        let rows = self.query(query, &[&latitude, &longitude])?;
+
+        self.collect_regions(rows)
     }
 
-    pub fn hierarchy_by_id<I>(&mut self, it: I) -> DatabaseResult<Vec<Hierarchy>>
+    pub fn children_by_id<I>(&mut self, it: I) -> DatabaseResult<HashMap<i64, Region>>
     where
         I: IntoIterator<Item = i64>,
     {
         let ids: Vec<i64> = it.into_iter().collect();
 
-        debug!("Get hierarchy by id: ids = {:?}", ids);
+        debug!("Get region children by id: ids = {:?}", ids);
+
+        let query = self
+            .query_schema
+            .children_by_id()
+            .ok_or_else(|| DatabaseError::query_not_defined_error("children_by_id"))?;
+        let rows = self.query(query, &[&ids])?;
+
+        self.collect_regions(rows)
+    }
+
+    /// Returns all region names without language restrictions.
+    pub fn all_regions(&mut self) -> DatabaseResult<Vec<RegionRecord>> {
+        debug!("Get all regions");
+
+        let query = self
+            .query_schema
+            .all_regions()
+            .ok_or_else(|| DatabaseError::query_not_defined_error("all_regions"))?;
+        let rows = self.query(query, &[])?;
+
+        rows.iter().map(RegionRecord::from_row).collect()
+    }
 
+    #[inline]
+    fn collect_regions(&self, result: Vec<Row>) -> DatabaseResult<HashMap<i64, Region>> {
+        let records = result
+            .iter()
+            .map(RegionRecord::from_row)
+            .collect::<DatabaseResult<Vec<_>>>()?;
+
+        Ok(self.filter.build_regions(&records))
+    }
+
+    pub fn hierarchy_by_id<I>(&mut self, it: I) -> DatabaseResult<Vec<Hierarchy>>
+    where
+        I: IntoIterator<Item = i64>,
+    {
         let mut result = Vec::new();
+        let mut ids: Vec<i64> = Vec::new();
+
+        for id in it {
+            match self.cache.get(&self.hierarchy_key(id)) {
+                Some(CacheValue::Hierarchies(hierarchies)) => result.extend(hierarchies),
+                _ => ids.push(id),
+            }
+        }
+
+        if ids.is_empty() {
+            return Ok(result);
+        }
+
+        debug!("Get hierarchy by id: ids = {:?}", ids);
 
-        for row in self
-            .client
-            .query(self.query_schema.hierarchy_by_id(), &[&ids])
-            .map_err(DatabaseError::query_execution_error)?
-        {
-            let id: i64 = row.try_get(0).map_err(DatabaseError::value_error)?;
-            let region_id: i64 = row.try_get(1).map_err(DatabaseError::value_error)?;
-            let level_1: Option<i64> = row.try_get(2).map_err(DatabaseError::value_error)?;
-            let level_2: Option<i64> = row.try_get(3).map_err(DatabaseError::value_error)?;
-            let level_3: Option<i64> = row.try_get(4).map_err(DatabaseError::value_error)?;
-            let level_4: Option<i64> = row.try_get(5).map_err(DatabaseError::value_error)?;
-            let level_5: Option<i64> = row.try_get(6).map_err(DatabaseError::value_error)?;
+        let rows = self.query_prepared(self.query_schema.hierarchy_by_id(), &[&ids])?;
+        let hierarchies = self.collect_hierarchies(rows)?;
+        let mut hierarchies_by_id: HashMap<_, Vec<_>> =
+            ids.iter().map(|&id| (id, Vec::new())).collect();
 
-            result.push(Hierarchy::new(
-                id, region_id, level_1, level_2, level_3, level_4, level_5,
-            ));
+        for hierarchy in &hierarchies {
+            if let Some(id_hierarchies) = hierarchies_by_id.get_mut(&hierarchy.id()) {
+                id_hierarchies.push(hierarchy.clone());
+            }
         }
 
+        for (id, id_hierarchies) in hierarchies_by_id {
+            self.cache.insert(
+                self.hierarchy_key(id),
+                CacheValue::Hierarchies(id_hierarchies),
+            );
+        }
+
+        result.extend(hierarchies);
+
         Ok(result)
     }
+
+    fn region_key(&self, id: i64) -> CacheKey {
+        CacheKey::RegionById {
+            connection: self.connection_key.clone(),
+            languages: self.language_key.clone(),
+            id,
+        }
+    }
+
+    fn hierarchy_key(&self, id: i64) -> CacheKey {
+        CacheKey::HierarchyById {
+            connection: self.connection_key.clone(),
+            id,
+        }
+    }
+
+    pub fn all_hierarchies(&mut self) -> DatabaseResult<Vec<Hierarchy>> {
+        debug!("Get all hierarchies");
+
+        let query = self
+            .query_schema
+            .all_hierarchies()
+            .ok_or_else(|| DatabaseError::query_not_defined_error("all_hierarchies"))?;
+        let rows = self.query(query, &[])?;
+
+        self.collect_hierarchies(rows)
+    }
+
+    #[inline]
+    fn collect_hierarchies(&self, result: Vec<Row>) -> DatabaseResult<Vec<Hierarchy>> {
+        let mut hierarchies = Vec::with_capacity(result.len());
+        let level_columns = match result.first() {
+            Some(row) => level_columns(row)?,
+            None => Vec::new(),
+        };
+
+        for row in result {
+            let id: i64 = column(&row, "id")?;
+            let region_id: i64 = column(&row, "region_id")?;
+            let levels = level_columns
+                .iter()
+                .map(|&index| row.try_get(index).map_err(DatabaseError::value_error))
+                .collect::<DatabaseResult<Vec<Option<i64>>>>()?;
+
+            hierarchies.push(Hierarchy::new(id, region_id, &levels));
+        }
+
+        Ok(hierarchies)
+    }
+}
+
+/// Returns true if error is caused by prepared statement invalidated by schema change.
+fn is_stale_statement(err: &postgres::Error) -> bool {
+    match err.code() {
+        Some(code) => {
+            *code == SqlState::FEATURE_NOT_SUPPORTED
+                || *code == SqlState::INVALID_SQL_STATEMENT_NAME
+        }
+        None => false,
+    }
+}
+
+/// Returns value of column with given name.
+fn column<'r, T>(row: &'r Row, name: &str) -> DatabaseResult<T>
+where
+    T: FromSql<'r>,
+{
+    let index = row
+        .columns()
+        .iter()
+        .position(|column| column.name() == name)
+        .ok_or_else(|| DatabaseError::missing_column_error(name))?;
+
+    row.try_get(index).map_err(DatabaseError::value_error)
+}
+
+/// Returns indices of `level_N` columns ordered by level number.
+fn level_columns(row: &Row) -> DatabaseResult<Vec<usize>> {
+    level_indices(row.columns().iter().map(|column| column.name()))
+}
+
+/// Returns indices of `level_N` names ordered by level number.
+fn level_indices<'n, I>(names: I) -> DatabaseResult<Vec<usize>>
+where
+    I: IntoIterator<Item = &'n str>,
+{
+    let mut levels: Vec<(u32, usize)> = names
+        .into_iter()
+        .enumerate()
+        .filter_map(|(index, name)| {
+            name.strip_prefix("level_")
+                .and_then(|level| level.parse().ok())
+                .map(|level| (level, index))
+        })
+        .collect();
+
+    if levels.is_empty() {
+        return Err(DatabaseError::missing_column_error("level_1"));
+    }
+
+    levels.sort_unstable();
+
+    Ok(levels.into_iter().map(|(_, index)| index).collect())
+}
+
+/// Cancels running query when timeout expires. Watchdog stops when dropped, drop waits for
+/// started cancel request, so it never cancels query of the next user of pooled connection.
+struct Watchdog {
+    stop: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Watchdog {
+    fn start(cancel_token: CancelToken, timeout: Duration) -> Watchdog {
+        let (stop, stopped) = mpsc::channel::<()>();
+
+        let thread = thread::spawn(move || {
+            if let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(timeout) {
+                warn!("Query execution timed out, canceling query");
+
+                if let Err(error) = cancel_token.cancel_query(NoTls) {
+                    warn!("Failed to cancel query - {}", error);
+                }
+            }
+        });
+
+        Watchdog {
+            stop: Some(stop),
+            thread: Some(thread),
+        }
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        // Closed channel wakes up waiting thread.
+        self.stop.take();
+
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() {
+                warn!("Query watchdog thread panicked");
+            }
+        }
+    }
+}
+
+/// Single region name as returned by region queries.
+#[derive(Debug, Clone)]
+pub struct RegionRecord {
+    id: i64,
+    language: String,
+    name: String,
+    is_default: bool,
+    attributes: Vec<(String, AttributeValue)>,
+}
+
+const REGION_COLUMNS: &[&str] = &["id", "language_code", "name", "is_default"];
+
+impl RegionRecord {
+    pub fn new(
+        id: i64,
+        language: &str,
+        name: &str,
+        is_default: bool,
+        attributes: Vec<(String, AttributeValue)>,
+    ) -> RegionRecord {
+        RegionRecord {
+            id,
+            language: language.into(),
+            name: name.into(),
+            is_default,
+            attributes,
+        }
+    }
+
+    fn from_row(row: &Row) -> DatabaseResult<RegionRecord> {
+        let mut attributes = Vec::new();
+
+        for (index, column) in row.columns().iter().enumerate() {
+            if !REGION_COLUMNS.contains(&column.name()) {
+                if let Some(value) = attribute_value(row, index)? {
+                    attributes.push((column.name().to_string(), value));
+                }
+            }
+        }
+
+        Ok(RegionRecord::new(
+            column(row, "id")?,
+            column(row, "language_code")?,
+            column(row, "name")?,
+            column(row, "is_default")?,
+            attributes,
+        ))
+    }
+
+    pub fn id(&self) -> i64 {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// Value of extra region column.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum AttributeValue {
+    Boolean(bool),
+    Integer(i64),
+    Float(f64),
+    Text(String),
+}
+
+impl AttributeValue {
+    pub fn to_text(&self) -> String {
+        match self {
+            AttributeValue::Boolean(value) => value.to_string(),
+            AttributeValue::Integer(value) => value.to_string(),
+            AttributeValue::Float(value) => value.to_string(),
+            AttributeValue::Text(value) => value.clone(),
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        match self {
+            AttributeValue::Text(text) => text.len(),
+            _ => 0,
+        }
+    }
+}
+
+/// Reads extra column value. Null values and values of unsupported types are skipped.
+fn attribute_value(row: &Row, index: usize) -> DatabaseResult<Option<AttributeValue>> {
+    let column = &row.columns()[index];
+    let value = match *column.type_() {
+        Type::BOOL => row
+            .try_get::<_, Option<bool>>(index)
+            .map(|value| value.map(AttributeValue::Boolean)),
+        Type::INT2 => row
+            .try_get::<_, Option<i16>>(index)
+            .map(|value| value.map(|value| AttributeValue::Integer(value.into()))),
+        Type::INT4 => row
+            .try_get::<_, Option<i32>>(index)
+            .map(|value| value.map(|value| AttributeValue::Integer(value.into()))),
+        Type::INT8 => row
+            .try_get::<_, Option<i64>>(index)
+            .map(|value| value.map(AttributeValue::Integer)),
+        Type::FLOAT4 => row
+            .try_get::<_, Option<f32>>(index)
+            .map(|value| value.map(|value| AttributeValue::Float(value.into()))),
+        Type::FLOAT8 => row
+            .try_get::<_, Option<f64>>(index)
+            .map(|value| value.map(AttributeValue::Float)),
+        Type::TEXT | Type::VARCHAR | Type::BPCHAR | Type::NAME => row
+            .try_get::<_, Option<String>>(index)
+            .map(|value| value.map(AttributeValue::Text)),
+        ref column_type => {
+            warn!(
+                "Column `{}` has unsupported type {}, cast it to text",
+                column.name(),
+                column_type
+            );
+
+            return Ok(None);
+        }
+    };
+
+    value.map_err(DatabaseError::value_error)
+}
+
+/// Builds regions from region names using requested languages and query schema language
+/// priority. Region type is taken from query schema type attribute.
+#[derive(Debug, Clone)]
+pub struct LanguageFilter {
+    languages: HashSet<String>,
+    language_priority: Vec<String>,
+    type_attribute: Option<String>,
+}
+
+impl LanguageFilter {
+    pub fn new(languages: &[String], query_schema: &QuerySchemaSettings) -> LanguageFilter {
+        LanguageFilter {
+            languages: languages.iter().cloned().collect(),
+            language_priority: languages
+                .iter()
+                .chain(query_schema.language_priority())
+                .cloned()
+                .collect(),
+            type_attribute: query_schema.type_attribute().map(String::from),
+        }
+    }
+
+    pub fn has_region_types(&self) -> bool {
+        self.type_attribute.is_some()
+    }
+
+    fn is_language_allowed(&self, language: &str) -> bool {
+        self.languages.is_empty() || self.languages.contains(language)
+    }
+
+    pub fn build_regions<'r, I>(&self, records: I) -> HashMap<i64, Region>
+    where
+        I: IntoIterator<Item = &'r RegionRecord>,
+    {
+        let mut builders = HashMap::new();
+
+        for record in records {
+            let builder = builders.entry(record.id).or_insert_with(RegionBuilder::new);
+
+            builder.insert_attributes(&record.attributes);
+
+            if self.is_language_allowed(&record.language) {
+                builder.insert_name(
+                    record.language.clone(),
+                    record.name.clone(),
+                    record.is_default,
+                );
+            } else {
+                builder.insert_filtered_name(&record.name, record.is_default);
+            }
+        }
+
+        builders
+            .into_iter()
+            .map(|(id, builder)| {
+                (
+                    id,
+                    builder.build(&self.language_priority, self.type_attribute.as_deref()),
+                )
+            })
+            .collect()
+    }
 }
 
 #[derive(Debug)]
 struct RegionBuilder {
     default_name: Option<String>,
+    filtered_name: Option<String>,
     names: Vec<RegionName>,
     lower_name_set: HashSet<String>,
+    attributes: BTreeMap<String, AttributeValue>,
 }
 
 impl RegionBuilder {
     fn new() -> RegionBuilder {
         RegionBuilder {
             default_name: None,
+            filtered_name: None,
             names: Vec::new(),
             lower_name_set: HashSet::new(),
+            attributes: BTreeMap::new(),
+        }
+    }
+
+    fn insert_attributes(&mut self, attributes: &[(String, AttributeValue)]) {
+        for (name, value) in attributes {
+            self.attributes.insert(name.clone(), value.clone());
         }
     }
 
@@ -167,49 +794,113 @@ impl RegionBuilder {
         self.lower_name_set.insert(name.to_lowercase());
     }
 
-    fn build(self) -> Region {
+    /// Keeps name of not requested language, it is used as default name if region has no name of
+    /// requested languages. Default name is preferred over the first name.
+    fn insert_filtered_name(&mut self, name: &str, is_default: bool) {
+        if is_default || self.filtered_name.is_none() {
+            self.filtered_name = Some(name.into());
+        }
+    }
+
+    fn build(self, language_priority: &[String], type_attribute: Option<&str>) -> Region {
+        let names = &self.names;
         let default_name = self
             .default_name
+            .clone()
+            .or_else(|| {
+                language_priority.iter().find_map(|language| {
+                    names
+                        .iter()
+                        .find(|name| name.language() == language)
+                        .map(|name| name.name().into())
+                })
+            })
+            .or_else(|| names.first().map(|name| name.name().into()))
+            .or_else(|| self.filtered_name.clone())
             .unwrap_or_else(|| "<no default name>".into());
+        let region_type = type_attribute
+            .and_then(|name| self.attributes.get(name))
+            .map(AttributeValue::to_text);
 
-        Region::new(default_name, self.names, self.lower_name_set)
+        Region::new(
+            default_name,
+            self.default_name.is_some(),
+            self.names,
+            self.lower_name_set,
+            self.attributes,
+            region_type,
+        )
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct Region {
     default_name: String,
+    has_default_name: bool,
     names: Vec<RegionName>,
     lower_name_set: HashSet<String>,
+    attributes: BTreeMap<String, AttributeValue>,
+    region_type: Option<String>,
 }
 
 impl Region {
     fn new(
         default_name: String,
+        has_default_name: bool,
         names: Vec<RegionName>,
         lower_name_set: HashSet<String>,
+        attributes: BTreeMap<String, AttributeValue>,
+        region_type: Option<String>,
     ) -> Region {
         Region {
             default_name,
+            has_default_name,
             names,
             lower_name_set,
+            attributes,
+            region_type,
         }
     }
 
+    /// Returns value of query schema type attribute as text.
+    pub fn region_type(&self) -> Option<&str> {
+        self.region_type.as_deref()
+    }
+
     pub fn default_name(&self) -> &str {
         &self.default_name
     }
 
+    /// Returns `false` if default name was selected using language priority.
+    pub fn has_default_name(&self) -> bool {
+        self.has_default_name
+    }
+
     pub fn names(&self) -> &[RegionName] {
         &self.names
     }
 
-    pub fn contains_name(&self, pattern: &str) -> bool {
-        let pattern = pattern.to_lowercase();
+    /// Returns values of extra columns of region queries.
+    pub fn attributes(&self) -> &BTreeMap<String, AttributeValue> {
+        &self.attributes
+    }
+
+    /// Checks that at least one lower case region name satisfies given predicate.
+    pub fn matches_name<F>(&self, predicate: F) -> bool
+    where
+        F: Fn(&str) -> bool,
+    {
+        self.lower_name_set.iter().any(|name| predicate(name))
+    }
 
-        self.lower_name_set
+    /// Returns the first region name which lower case form satisfies given predicate.
+    pub fn find_name<F>(&self, predicate: F) -> Option<&RegionName>
+    where
+        F: Fn(&str) -> bool,
+    {
+        self.names
             .iter()
-            .any(|name| name.contains(&pattern))
+            .find(|name| predicate(&name.name().to_lowercase()))
     }
 }
 
@@ -238,27 +929,24 @@ pub struct Hierarchy {
     id: i64,
     region_id: i64,
     parts: Vec<i64>,
+    has_gap: bool,
 }
 
 impl Hierarchy {
-    fn new(
-        id: i64,
-        region_id: i64,
-        level_1: Option<i64>,
-        level_2: Option<i64>,
-        level_3: Option<i64>,
-        level_4: Option<i64>,
-        level_5: Option<i64>,
-    ) -> Hierarchy {
-        let parts = [level_1, level_2, level_3, level_4, level_5]
-            .iter()
-            .filter_map(|&part| part)
-            .collect();
+    /// Creates hierarchy from level values ordered from top level region, `None` means missing
+    /// level.
+    pub fn new(id: i64, region_id: i64, levels: &[Option<i64>]) -> Hierarchy {
+        let parts = levels.iter().filter_map(|&part| part).collect();
+        // Missing level followed by present level means gap in hierarchy.
+        let has_gap = levels
+            .windows(2)
+            .any(|window| window[0].is_none() && window[1].is_some());
 
         Hierarchy {
             id,
             region_id,
             parts,
+            has_gap,
         }
     }
 
@@ -273,4 +961,132 @@ impl Hierarchy {
     pub fn parts(&self) -> &[i64] {
         &self.parts
     }
+
+    pub fn has_gap(&self) -> bool {
+        self.has_gap
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query_schema(yaml: &str) -> QuerySchemaSettings {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    fn record(id: i64, language: &str, name: &str, is_default: bool) -> RegionRecord {
+        RegionRecord::new(id, language, name, is_default, Vec::new())
+    }
+
+    fn languages(languages: &[&str]) -> Vec<String> {
+        languages.iter().map(|&language| language.into()).collect()
+    }
+
+    #[test]
+    fn build_uses_default_name() {
+        let filter = LanguageFilter::new(&[], &query_schema("{}"));
+        let regions = filter.build_regions(&[
+            record(1, "fr", "Londres", false),
+            record(1, "en", "London", true),
+        ]);
+        let region = &regions[&1];
+
+        assert_eq!(region.default_name(), "London");
+        assert!(region.has_default_name());
+        assert_eq!(region.names().len(), 2);
+    }
+
+    #[test]
+    fn build_prefers_requested_languages_over_schema_priority() {
+        let schema = query_schema("language_priority: [de, fr]");
+        let records = [
+            record(1, "fr", "Londres", false),
+            record(1, "de", "London DE", false),
+            record(1, "es", "Londres ES", false),
+        ];
+
+        let filter = LanguageFilter::new(&[], &schema);
+        let region = &filter.build_regions(&records)[&1];
+
+        assert_eq!(region.default_name(), "London DE");
+        assert!(!region.has_default_name());
+
+        let filter = LanguageFilter::new(&languages(&["es", "fr"]), &schema);
+        let region = &filter.build_regions(&records)[&1];
+
+        assert_eq!(region.default_name(), "Londres ES");
+    }
+
+    #[test]
+    fn build_falls_back_to_first_name() {
+        let filter = LanguageFilter::new(&[], &query_schema("language_priority: [de]"));
+        let regions = filter.build_regions(&[record(1, "fr", "Londres", false)]);
+
+        assert_eq!(regions[&1].default_name(), "Londres");
+    }
+
+    #[test]
+    fn build_keeps_regions_without_allowed_names() {
+        let filter = LanguageFilter::new(&languages(&["fr"]), &query_schema("{}"));
+        let regions = filter.build_regions(&[
+            record(1, "en", "London", true),
+            record(2, "fr", "Paris", true),
+        ]);
+
+        assert!(regions[&1].names().is_empty());
+        assert_eq!(regions[&1].default_name(), "London");
+        assert!(!regions[&1].has_default_name());
+        assert!(regions[&2].matches_name(|name| name == "paris"));
+    }
+
+    #[test]
+    fn build_falls_back_to_filtered_name() {
+        let filter = LanguageFilter::new(&languages(&["fr"]), &query_schema("{}"));
+        let regions = filter.build_regions(&[
+            record(1, "de", "London DE", false),
+            record(1, "en", "London", true),
+            record(1, "es", "Londres ES", false),
+            record(2, "de", "Paris DE", false),
+            record(2, "fr", "Paris", false),
+        ]);
+
+        assert_eq!(regions[&1].default_name(), "London");
+        assert_eq!(regions[&2].default_name(), "Paris");
+    }
+
+    #[test]
+    fn build_sets_region_type_from_type_attribute() {
+        let filter = LanguageFilter::new(&[], &query_schema("type_attribute: admin_level"));
+        let attributes = vec![
+            ("admin_level".to_string(), AttributeValue::Integer(4)),
+            ("kind".to_string(), AttributeValue::Text("city".into())),
+        ];
+        let regions = filter.build_regions(&[
+            RegionRecord::new(1, "en", "London", true, attributes),
+            record(2, "en", "Paris", true),
+        ]);
+
+        assert!(filter.has_region_types());
+        assert_eq!(regions[&1].region_type(), Some("4"));
+        assert_eq!(regions[&1].attributes().len(), 2);
+        assert_eq!(regions[&2].region_type(), None);
+    }
+
+    #[test]
+    fn hierarchy_detects_gaps() {
+        let hierarchy = Hierarchy::new(3, 3, &[Some(1), None, Some(3)]);
+
+        assert_eq!(hierarchy.parts(), &[1, 3]);
+        assert!(hierarchy.has_gap());
+        assert!(!Hierarchy::new(3, 3, &[Some(1), Some(3), None]).has_gap());
+    }
+
+    #[test]
+    fn level_indices_are_ordered_by_level_number() {
+        let names = ["id", "level_10", "level_2", "region_id", "level_1"];
+
+        assert_eq!(level_indices(names.iter().cloned()).unwrap(), vec![4, 2, 1]);
+        assert!(level_indices(["id", "region_id"].iter().cloned()).is_err());
+    }
 }
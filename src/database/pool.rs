@@ -0,0 +1,164 @@
+use postgres::Client;
+use postgres::Statement;
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::ops::DerefMut;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+/// Maximal number of idle connections kept for single connection settings.
+const MAX_IDLE_CONNECTIONS: usize = 8;
+
+/// Idle connections older than this are closed instead of reuse.
+const MAX_IDLE_TIME: Duration = Duration::from_secs(300);
+
+/// Physical database connection with statements prepared on it.
+pub struct PooledConnection {
+    client: Client,
+    statements: HashMap<String, Statement>,
+    released: Instant,
+}
+
+impl PooledConnection {
+    pub fn new(client: Client) -> PooledConnection {
+        PooledConnection {
+            client,
+            statements: HashMap::new(),
+            released: Instant::now(),
+        }
+    }
+
+    pub fn client(&mut self) -> &mut Client {
+        &mut self.client
+    }
+
+    pub fn statement(&self, query: &str) -> Option<Statement> {
+        self.statements.get(query).cloned()
+    }
+
+    pub fn insert_statement(&mut self, query: &str, statement: Statement) {
+        self.statements.insert(query.into(), statement);
+    }
+
+    pub fn remove_statement(&mut self, query: &str) {
+        self.statements.remove(query);
+    }
+}
+
+#[derive(Clone)]
+pub struct ClientPoolRef {
+    inner: Arc<Mutex<HashMap<String, Vec<PooledConnection>>>>,
+}
+
+impl ClientPoolRef {
+    fn with_pool<F, T>(&self, callback: F) -> Option<T>
+    where
+        F: FnOnce(&mut HashMap<String, Vec<PooledConnection>>) -> T,
+    {
+        match self.inner.lock() {
+            Ok(ref mut pool) => Some(callback(pool)),
+            Err(err) => {
+                warn!("Failed to acquire pool lock - {}", err);
+
+                None
+            }
+        }
+    }
+
+    /// Returns idle connection for given connection key if present.
+    pub fn acquire(&self, key: &str) -> Option<PoolGuard> {
+        let connection = self
+            .with_pool(|pool| {
+                let connections = pool.get_mut(key)?;
+
+                while let Some(connection) = connections.pop() {
+                    if !connection.client.is_closed()
+                        && connection.released.elapsed() < MAX_IDLE_TIME
+                    {
+                        return Some(connection);
+                    }
+                }
+
+                None
+            })
+            .unwrap_or(None)?;
+
+        Some(self.guard(key, connection))
+    }
+
+    pub fn guard(&self, key: &str, connection: PooledConnection) -> PoolGuard {
+        PoolGuard {
+            key: key.into(),
+            connection: Some(connection),
+            pool: self.clone(),
+        }
+    }
+
+    fn release(&self, key: String, mut connection: PooledConnection) {
+        if connection.client.is_closed() {
+            return;
+        }
+
+        connection.released = Instant::now();
+
+        self.with_pool(|pool| {
+            let connections = pool.entry(key).or_default();
+
+            if connections.len() < MAX_IDLE_CONNECTIONS {
+                connections.push(connection);
+            }
+        });
+    }
+
+    /// Closes all idle connections with given connection key.
+    pub fn invalidate(&self, key: &str) {
+        self.with_pool(|pool| pool.remove(key));
+    }
+}
+
+impl std::fmt::Debug for ClientPoolRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "ClientPoolRef")
+    }
+}
+
+/// Connection taken from pool, returned back to pool when dropped.
+pub struct PoolGuard {
+    key: String,
+    connection: Option<PooledConnection>,
+    pool: ClientPoolRef,
+}
+
+impl Deref for PoolGuard {
+    type Target = PooledConnection;
+
+    fn deref(&self) -> &PooledConnection {
+        self.connection
+            .as_ref()
+            .expect("Connection already released")
+    }
+}
+
+impl DerefMut for PoolGuard {
+    fn deref_mut(&mut self) -> &mut PooledConnection {
+        self.connection
+            .as_mut()
+            .expect("Connection already released")
+    }
+}
+
+impl Drop for PoolGuard {
+    fn drop(&mut self) {
+        if let Some(connection) = self.connection.take() {
+            self.pool.release(self.key.clone(), connection);
+        }
+    }
+}
+
+pub fn client_pool() -> ClientPoolRef {
+    ClientPoolRef {
+        inner: Arc::new(Mutex::new(HashMap::new())),
+    }
+}
@@ -0,0 +1,54 @@
+use crate::database::DatabaseClient;
+use crate::database::DatabaseResult;
+use crate::database::Hierarchy;
+use crate::database::Region;
+use std::collections::HashMap;
+
+/// Storage of region names and hierarchies used by region search. Every connection kind provides
+/// its own implementation.
+pub trait RegionSource {
+    /// Restricts region names to given languages, empty list allows all languages.
+    fn set_languages(&mut self, languages: &[String]);
+
+    /// Returns true if regions have type taken from type attribute.
+    fn has_region_types(&self) -> bool;
+
+    /// Returns regions with name matched to SQL `like` pattern. Wildcards and backslash are escaped
+    /// with backslash in pattern, so query must use `escape '\'` clause. Regions matched only by
+    /// names of not requested languages are returned without names.
+    fn regions_by_name(&mut self, name: &str) -> DatabaseResult<HashMap<i64, Region>>;
+
+    /// Returns regions with name matched to regular expression. Regions matched only by names of
+    /// not requested languages are returned without names.
+    fn regions_by_regex(&mut self, pattern: &str) -> DatabaseResult<HashMap<i64, Region>>;
+
+    fn regions_by_id(&mut self, ids: &[i64]) -> DatabaseResult<HashMap<i64, Region>>;
+
+    fn hierarchy_by_id(&mut self, ids: &[i64]) -> DatabaseResult<Vec<Hierarchy>>;
+}
+
+impl RegionSource for DatabaseClient<'_> {
+    fn set_languages(&mut self, languages: &[String]) {
+        DatabaseClient::set_languages(self, languages)
+    }
+
+    fn has_region_types(&self) -> bool {
+        DatabaseClient::has_region_types(self)
+    }
+
+    fn regions_by_name(&mut self, name: &str) -> DatabaseResult<HashMap<i64, Region>> {
+        DatabaseClient::regions_by_name(self, name)
+    }
+
+    fn regions_by_regex(&mut self, pattern: &str) -> DatabaseResult<HashMap<i64, Region>> {
+        DatabaseClient::regions_by_regex(self, pattern)
+    }
+
+    fn regions_by_id(&mut self, ids: &[i64]) -> DatabaseResult<HashMap<i64, Region>> {
+        DatabaseClient::regions_by_id(self, ids.iter().cloned())
+    }
+
+    fn hierarchy_by_id(&mut self, ids: &[i64]) -> DatabaseResult<Vec<Hierarchy>> {
+        DatabaseClient::hierarchy_by_id(self, ids.iter().cloned())
+    }
+}
@@ -0,0 +1,371 @@
+use crate::config::ConnectionSettings;
+use crate::config::QuerySchemaSettings;
+use crate::database;
+use crate::database::AttributeValue;
+use crate::database::DatabaseError;
+use crate::database::DatabaseResult;
+use crate::database::Hierarchy;
+use crate::database::LanguageFilter;
+use crate::database::Region;
+use crate::database::RegionRecord;
+use crate::database::RegionSource;
+use crate::database::REGION_COLUMNS;
+use regex::Regex;
+use regex::RegexBuilder;
+use rusqlite::functions::FunctionFlags;
+use rusqlite::types::Value;
+use rusqlite::Connection;
+use rusqlite::OpenFlags;
+use rusqlite::ToSql;
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+/// Number of SQLite virtual machine instructions between statement timeout checks.
+const PROGRESS_OPS: i32 = 1000;
+
+/// Region source reading SQLite database file. Database is opened read only. Array parameters
+/// are passed as JSON arrays, so queries read them using `json_each`.
+pub struct SqliteClient<'a> {
+    query_schema: &'a QuerySchemaSettings,
+    connection: Connection,
+    filter: LanguageFilter,
+    statement_timeout: Option<Duration>,
+}
+
+impl<'a> SqliteClient<'a> {
+    pub fn open(
+        settings: &ConnectionSettings,
+        query_schema: &'a QuerySchemaSettings,
+    ) -> DatabaseResult<SqliteClient<'a>> {
+        let connection = Connection::open_with_flags(
+            settings.database(),
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )
+        .map_err(DatabaseError::sqlite_connection_error)?;
+
+        // SQLite has no regular expression function, `x regexp y` calls `regexp(y, x)`. Matching is
+        // case insensitive like `~*` operator of PostgreSQL.
+        connection
+            .create_scalar_function(
+                "regexp",
+                2,
+                FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+                |context| {
+                    let regex: Arc<Regex> = context.get_or_create_aux(0, |value| {
+                        RegexBuilder::new(value.as_str()?)
+                            .case_insensitive(true)
+                            .build()
+                            .map_err(|error| Box::new(error) as Box<dyn Error + Send + Sync>)
+                    })?;
+                    let text = context.get::<Option<String>>(1)?;
+
+                    Ok(text.is_some_and(|text| regex.is_match(&text)))
+                },
+            )
+            .map_err(DatabaseError::sqlite_connection_error)?;
+
+        let statement_timeout = settings
+            .statement_timeout()
+            .or_else(|| query_schema.statement_timeout())
+            .map(Duration::from_millis);
+
+        Ok(SqliteClient {
+            query_schema,
+            connection,
+            filter: LanguageFilter::new(&[], query_schema),
+            statement_timeout,
+        })
+    }
+
+    /// Executes query and reads all rows. If statement timeout is set, query is interrupted when
+    /// timeout expires.
+    fn query(&self, query: &str, params: &[&dyn ToSql]) -> DatabaseResult<QueryResult> {
+        if let Some(timeout) = self.statement_timeout {
+            let started = Instant::now();
+
+            self.connection
+                .progress_handler(PROGRESS_OPS, Some(move || started.elapsed() > timeout));
+        }
+
+        let mut statement = self
+            .connection
+            .prepare_cached(query)
+            .map_err(DatabaseError::sqlite_query_execution_error)?;
+        let columns: Vec<String> = statement
+            .column_names()
+            .into_iter()
+            .map(String::from)
+            .collect();
+        let mut rows = statement
+            .query(params)
+            .map_err(DatabaseError::sqlite_query_execution_error)?;
+        let mut values = Vec::new();
+
+        while let Some(row) = rows
+            .next()
+            .map_err(DatabaseError::sqlite_query_execution_error)?
+        {
+            let row_values = (0..columns.len())
+                .map(|index| row.get::<_, Value>(index))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(DatabaseError::sqlite_query_execution_error)?;
+
+            values.push(row_values);
+        }
+
+        Ok(QueryResult {
+            columns,
+            rows: values,
+        })
+    }
+
+    fn collect_regions(&self, result: QueryResult) -> DatabaseResult<HashMap<i64, Region>> {
+        let records = result.region_records()?;
+
+        Ok(self.filter.build_regions(&records))
+    }
+}
+
+impl RegionSource for SqliteClient<'_> {
+    fn set_languages(&mut self, languages: &[String]) {
+        self.filter = LanguageFilter::new(languages, self.query_schema);
+    }
+
+    fn has_region_types(&self) -> bool {
+        self.filter.has_region_types()
+    }
+
+    fn regions_by_name(&mut self, name: &str) -> DatabaseResult<HashMap<i64, Region>> {
+        debug!("Get regions by name: name = {}", name);
+
+        let result = self.query(self.query_schema.regions_by_name(), &[&name])?;
+
+        self.collect_regions(result)
+    }
+
+    fn regions_by_regex(&mut self, pattern: &str) -> DatabaseResult<HashMap<i64, Region>> {
+        debug!("Get regions by regex: pattern = {}", pattern);
+
+        let query = self
+            .query_schema
+            .regions_by_regex()
+            .ok_or_else(|| DatabaseError::query_not_defined_error("regions_by_regex"))?;
+        let result = self.query(query, &[&pattern])?;
+
+        self.collect_regions(result)
+    }
+
+    fn regions_by_id(&mut self, ids: &[i64]) -> DatabaseResult<HashMap<i64, Region>> {
+        if ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        debug!("Get region names by id: ids = {:?}", ids);
+
+        let result = self.query(self.query_schema.regions_by_id(), &[&json_array(ids)])?;
+
+        self.collect_regions(result)
+    }
+
+    fn hierarchy_by_id(&mut self, ids: &[i64]) -> DatabaseResult<Vec<Hierarchy>> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        debug!("Get hierarchy by id: ids = {:?}", ids);
+
+        let result = self.query(self.query_schema.hierarchy_by_id(), &[&json_array(ids)])?;
+
+        result.hierarchies()
+    }
+}
+
+fn json_array(ids: &[i64]) -> String {
+    let ids: Vec<_> = ids.iter().map(i64::to_string).collect();
+
+    format!("[{}]", ids.join(","))
+}
+
+/// Rows of executed query with column names.
+struct QueryResult {
+    columns: Vec<String>,
+    rows: Vec<Vec<Value>>,
+}
+
+impl QueryResult {
+    fn column(&self, name: &str) -> DatabaseResult<usize> {
+        self.columns
+            .iter()
+            .position(|column| column == name)
+            .ok_or_else(|| DatabaseError::missing_column_error(name))
+    }
+
+    fn region_records(&self) -> DatabaseResult<Vec<RegionRecord>> {
+        let id = self.column("id")?;
+        let language = self.column("language_code")?;
+        let name = self.column("name")?;
+        let is_default = self.column("is_default")?;
+        let mut records = Vec::with_capacity(self.rows.len());
+
+        for row in &self.rows {
+            let attributes = self
+                .columns
+                .iter()
+                .zip(row)
+                .filter(|(column, _)| !REGION_COLUMNS.contains(&column.as_str()))
+                .filter_map(|(column, value)| {
+                    attribute_value(column, value).map(|value| (column.clone(), value))
+                })
+                .collect();
+
+            records.push(RegionRecord::new(
+                integer(&self.columns[id], &row[id])?,
+                &text(&self.columns[language], &row[language])?,
+                &text(&self.columns[name], &row[name])?,
+                integer(&self.columns[is_default], &row[is_default])? != 0,
+                attributes,
+            ));
+        }
+
+        Ok(records)
+    }
+
+    fn hierarchies(&self) -> DatabaseResult<Vec<Hierarchy>> {
+        let id = self.column("id")?;
+        let region_id = self.column("region_id")?;
+        let level_columns = database::level_indices(self.columns.iter().map(String::as_str))?;
+        let mut hierarchies = Vec::with_capacity(self.rows.len());
+
+        for row in &self.rows {
+            let levels = level_columns
+                .iter()
+                .map(|&index| match row[index] {
+                    Value::Null => Ok(None),
+                    ref value => integer(&self.columns[index], value).map(Some),
+                })
+                .collect::<DatabaseResult<Vec<_>>>()?;
+
+            hierarchies.push(Hierarchy::new(
+                integer(&self.columns[id], &row[id])?,
+                integer(&self.columns[region_id], &row[region_id])?,
+                &levels,
+            ));
+        }
+
+        Ok(hierarchies)
+    }
+}
+
+fn integer(name: &str, value: &Value) -> DatabaseResult<i64> {
+    match *value {
+        Value::Integer(value) => Ok(value),
+        _ => Err(DatabaseError::sqlite_value_error(name, "integer")),
+    }
+}
+
+fn text(name: &str, value: &Value) -> DatabaseResult<String> {
+    match value {
+        Value::Text(value) => Ok(value.clone()),
+        _ => Err(DatabaseError::sqlite_value_error(name, "text")),
+    }
+}
+
+/// Converts extra column value. Null values and blobs are skipped.
+fn attribute_value(name: &str, value: &Value) -> Option<AttributeValue> {
+    match value {
+        Value::Null => None,
+        Value::Integer(value) => Some(AttributeValue::Integer(*value)),
+        Value::Real(value) => Some(AttributeValue::Float(*value)),
+        Value::Text(value) => Some(AttributeValue::Text(value.clone())),
+        Value::Blob(_) => {
+            warn!("Column `{}` contains blob value, cast it to text", name);
+
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+
+    const QUERY_SCHEMA: &str = r#"
+dialect: sqlite
+regions_by_name: |
+  select region_id as id, language_code, name, is_default
+  from region_names
+  where region_id in (select region_id from region_names where name like ?1 escape '\')
+regions_by_regex: |
+  select region_id as id, language_code, name, is_default
+  from region_names
+  where region_id in (select region_id from region_names where lower(name) regexp ?1)
+"#;
+
+    /// Runs callback with client of SQLite database containing given region names.
+    fn with_client<F>(name: &str, names: &[(i64, &str)], callback: F)
+    where
+        F: FnOnce(&mut SqliteClient),
+    {
+        let path = env::temp_dir().join(format!("find_region_{}_{}.db", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        let connection = Connection::open(&path).unwrap();
+
+        connection
+            .execute_batch(
+                "create table region_names \
+                 (region_id integer, language_code text, name text, is_default integer)",
+            )
+            .unwrap();
+
+        for &(id, name) in names {
+            connection
+                .execute(
+                    "insert into region_names values (?1, 'en', ?2, 1)",
+                    rusqlite::params![id, name],
+                )
+                .unwrap();
+        }
+
+        drop(connection);
+
+        let query_schema: QuerySchemaSettings = serde_yaml::from_str(QUERY_SCHEMA).unwrap();
+        let settings: ConnectionSettings = serde_yaml::from_str(&format!(
+            "{{ description: Test, query_schema: LITE, kind: sqlite, database: '{}' }}",
+            path.display()
+        ))
+        .unwrap();
+
+        callback(&mut SqliteClient::open(&settings, &query_schema).unwrap());
+        fs::remove_file(&path).unwrap();
+    }
+
+    fn ids(regions: HashMap<i64, Region>) -> Vec<i64> {
+        let mut ids: Vec<_> = regions.into_keys().collect();
+
+        ids.sort_unstable();
+        ids
+    }
+
+    #[test]
+    fn find_regions_by_mixed_case_regex() {
+        with_client("regex", &[(1, "London"), (2, "Paris")], |client| {
+            assert_eq!(ids(client.regions_by_regex("^Lon").unwrap()), vec![1]);
+            assert_eq!(ids(client.regions_by_regex("PAR|lon").unwrap()), vec![1, 2]);
+        });
+    }
+
+    #[test]
+    fn find_regions_by_escaped_like_pattern() {
+        let names = [(1, "100% Park"), (2, "1000 Parks"), (3, "a_b\\c")];
+
+        with_client("like", &names, |client| {
+            assert_eq!(ids(client.regions_by_name("%100\\%%").unwrap()), vec![1]);
+            assert_eq!(ids(client.regions_by_name("a\\_b\\\\c").unwrap()), vec![3]);
+        });
+    }
+}
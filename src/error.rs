@@ -1,4 +1,5 @@
 use crate::config::ConfigError;
+use crate::index::RegionIndexError;
 use crate::worker::UpdateConnectionsError;
 use iron::error::HttpError;
 use std::error::Error;
@@ -9,10 +10,12 @@ use std::fmt::Result as FmtResult;
 pub type ApplicationResult = Result<(), ApplicationError>;
 
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum ApplicationError {
     LoadConfigError { message: String },
     ConfigError { message: String },
     UpdateConnectionsError { message: String },
+    PreloadError { message: String },
     ServerError { message: String },
 }
 
@@ -41,6 +44,14 @@ impl ApplicationError {
         }
     }
 
+    pub fn preload_error(error: RegionIndexError) -> ApplicationError {
+        error!("Preload error - {}", error);
+
+        ApplicationError::PreloadError {
+            message: format!("{}", error),
+        }
+    }
+
     pub fn server_error(error: HttpError) -> ApplicationError {
         error!("Server error - {}", error);
 
@@ -58,6 +69,7 @@ impl Display for ApplicationError {
             ApplicationError::LoadConfigError { message } => write!(f, "{}", message),
             ApplicationError::ConfigError { message } => write!(f, "{}", message),
             ApplicationError::UpdateConnectionsError { message } => write!(f, "{}", message),
+            ApplicationError::PreloadError { message } => write!(f, "{}", message),
             ApplicationError::ServerError { message } => write!(f, "{}", message),
         }
     }
@@ -0,0 +1,23 @@
+use super::util::handle_empty;
+use crate::cache::RegionCacheRef;
+use iron::middleware::Handler;
+use iron::IronResult;
+use iron::Request as IromRequest;
+use iron::Response as IromResponse;
+
+#[derive(Debug)]
+pub struct CacheHandler {
+    region_cache: RegionCacheRef,
+}
+
+impl CacheHandler {
+    pub fn new(region_cache: RegionCacheRef) -> CacheHandler {
+        CacheHandler { region_cache }
+    }
+}
+
+impl Handler for CacheHandler {
+    fn handle(&self, _req: &mut IromRequest) -> IronResult<IromResponse> {
+        handle_empty(move || Ok(self.region_cache.statistics()))
+    }
+}
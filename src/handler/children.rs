@@ -0,0 +1,69 @@
+use crate::cache::RegionCacheRef;
+use crate::config::ConfigRef;
+use crate::database::ClientPoolRef;
+use crate::handler::common;
+use crate::handler::common::Response;
+use crate::handler::error::HandlerError;
+use crate::handler::util::handle_request;
+use crate::manager::DynamicConnectionsRef;
+use iron::middleware::Handler;
+use iron::IronResult;
+use iron::Request as IromRequest;
+use iron::Response as IromResponse;
+
+#[derive(Debug)]
+pub struct ChildrenHandler {
+    config: ConfigRef,
+    dynamic_connections: DynamicConnectionsRef,
+    region_cache: RegionCacheRef,
+    client_pool: ClientPoolRef,
+}
+
+impl ChildrenHandler {
+    pub fn new(
+        config: ConfigRef,
+        dynamic_connections: DynamicConnectionsRef,
+        region_cache: RegionCacheRef,
+        client_pool: ClientPoolRef,
+    ) -> ChildrenHandler {
+        ChildrenHandler {
+            config,
+            dynamic_connections,
+            region_cache,
+            client_pool,
+        }
+    }
+}
+
+impl Handler for ChildrenHandler {
+    fn handle(&self, request: &mut IromRequest) -> IronResult<IromResponse> {
+        handle_request(request, move |request: Request| {
+            let mut client = common::prepare_connection(
+                &self.config,
+                &self.dynamic_connections,
+                &self.region_cache,
+                &self.client_pool,
+                request.connection,
+            )?;
+
+            client.set_languages(&request.languages);
+
+            let children = client.children_by_id(vec![request.id]).map_err(|error| {
+                HandlerError::database_error("Failed to query region children", error)
+            })?;
+            let hierarchies = common::collect_hierarchy(&mut client, children.keys().cloned())?;
+            let all_regions = common::collect_all_regions(&mut client, &children, &hierarchies)?;
+
+            Ok(Response::new(all_regions, &hierarchies))
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Request {
+    connection: usize,
+    id: i64,
+    #[serde(default)]
+    languages: Vec<String>,
+}
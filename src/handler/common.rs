@@ -0,0 +1,797 @@
+use crate::cache::RegionCacheRef;
+use crate::config::ConfigRef;
+use crate::config::ConnectionKind;
+use crate::config::ConnectionSettings;
+use crate::config::QuerySchemaSettings;
+use crate::database::AttributeValue;
+use crate::database::ClientPoolRef;
+use crate::database::Database;
+use crate::database::DatabaseClient;
+use crate::database::Hierarchy as DbHierarchy;
+use crate::database::Region as DbRegion;
+use crate::database::RegionSource;
+use crate::database::SqliteClient;
+use crate::handler::error::HandlerError;
+use crate::handler::error::HandlerResult;
+use crate::handler::query;
+use crate::handler::query::MatchMode;
+use crate::handler::query::PartMatch;
+use crate::handler::query::Query;
+use crate::handler::query::Rejection;
+use crate::index::IndexSource;
+use crate::index::RegionIndexRef;
+use crate::manager::DynamicConnectionsRef;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+const TYPE_ATTRIBUTE_NOT_DEFINED: &str =
+    "Region types can not be used, query schema does not define type attribute";
+
+pub fn connection_settings(
+    config: &ConfigRef,
+    dynamic_connections: &DynamicConnectionsRef,
+    index: usize,
+) -> HandlerResult<ConnectionSettings> {
+    let dynamic_connection = dynamic_connections.get(index).unwrap_or(None);
+    let static_connection = config.connections().static_connections().get(index);
+
+    match static_connection.cloned().or(dynamic_connection) {
+        Some(connection) => Ok(connection),
+        None => Err(HandlerError::new(&format!(
+            "Invalid connection index `{}`",
+            index
+        ))),
+    }
+}
+
+pub fn all_connections(
+    config: &ConfigRef,
+    dynamic_connections: &DynamicConnectionsRef,
+) -> Vec<(usize, ConnectionSettings)> {
+    let mut connections: Vec<_> = config
+        .connections()
+        .static_connections()
+        .iter()
+        .cloned()
+        .enumerate()
+        .collect();
+    let _ = dynamic_connections
+        .for_each(|index, connection| connections.push((index, connection.clone())));
+
+    connections
+}
+
+/// Returns query schema of connection. Query schema dialect must match connection kind.
+fn query_schema<'a>(
+    config: &'a ConfigRef,
+    connection: &ConnectionSettings,
+) -> HandlerResult<&'a QuerySchemaSettings> {
+    match config.query_schemas().get(connection.query_schema()) {
+        Some(query_schema) if query_schema.dialect() == connection.kind() => Ok(query_schema),
+        Some(_) => Err(HandlerError::new(&format!(
+            "Query schema `{}` dialect does not match kind of connection `{}`",
+            connection.query_schema(),
+            connection.description(),
+        ))),
+        None => Err(HandlerError::new(&format!(
+            "Invalid query schema `{}` in connection `{}`",
+            connection.query_schema(),
+            connection.description(),
+        ))),
+    }
+}
+
+pub fn prepare_connection<'a>(
+    config: &'a ConfigRef,
+    dynamic_connections: &DynamicConnectionsRef,
+    region_cache: &RegionCacheRef,
+    client_pool: &ClientPoolRef,
+    index: usize,
+) -> HandlerResult<DatabaseClient<'a>> {
+    let connection = connection_settings(config, dynamic_connections, index)?;
+    let query_schema = query_schema(config, &connection)?;
+
+    if connection.kind() != ConnectionKind::Postgres {
+        return Err(HandlerError::new(&format!(
+            "Request is not supported by {:?} connection `{}`",
+            connection.kind(),
+            connection.description(),
+        )));
+    }
+
+    Database::new(
+        connection,
+        query_schema,
+        region_cache.clone(),
+        client_pool.clone(),
+    )
+    .connect()
+    .map_err(|_| HandlerError::new("Failed to connect to database"))
+}
+
+/// Returns region source of connection with given index. Preloaded connections are served from
+/// loaded region index, until the first load completes postgres connections use database.
+pub fn region_source<'a>(
+    config: &'a ConfigRef,
+    dynamic_connections: &DynamicConnectionsRef,
+    region_cache: &RegionCacheRef,
+    client_pool: &ClientPoolRef,
+    region_index: &RegionIndexRef,
+    index: usize,
+) -> HandlerResult<Box<dyn RegionSource + 'a>> {
+    let connection = connection_settings(config, dynamic_connections, index)?;
+
+    if let Some(loaded_index) = region_index.get(index) {
+        let query_schema = query_schema(config, &connection)?;
+
+        return Ok(Box::new(IndexSource::new(loaded_index, query_schema)));
+    }
+
+    match connection.kind() {
+        ConnectionKind::Postgres => {
+            let client = prepare_connection(
+                config,
+                dynamic_connections,
+                region_cache,
+                client_pool,
+                index,
+            )?;
+
+            Ok(Box::new(client))
+        }
+        ConnectionKind::Sqlite => {
+            let query_schema = query_schema(config, &connection)?;
+            let client = SqliteClient::open(&connection, query_schema)
+                .map_err(|_| HandlerError::new("Failed to open database file"))?;
+
+            Ok(Box::new(client))
+        }
+        ConnectionKind::File => Err(HandlerError::new(&format!(
+            "Connection `{}` is not loaded",
+            connection.description()
+        ))),
+    }
+}
+
+pub fn collect_hierarchy<I>(source: &mut dyn RegionSource, it: I) -> HandlerResult<Vec<DbHierarchy>>
+where
+    I: IntoIterator<Item = i64>,
+{
+    let ids: Vec<_> = it.into_iter().collect();
+
+    source
+        .hierarchy_by_id(&ids)
+        .map_err(|error| HandlerError::database_error("Failed to query hierarchy", error))
+}
+
+pub fn collect_all_regions(
+    source: &mut dyn RegionSource,
+    regions: &HashMap<i64, DbRegion>,
+    hierarchies: &[DbHierarchy],
+) -> HandlerResult<HashMap<i64, DbRegion>> {
+    let mut region_ids = HashSet::new();
+
+    for hierarchy in hierarchies {
+        region_ids.insert(hierarchy.id());
+        region_ids.extend(hierarchy.parts());
+    }
+
+    let region_ids: Vec<_> = region_ids
+        .into_iter()
+        .filter(|region_id| !regions.contains_key(region_id))
+        .collect();
+    let extended_regions = source
+        .regions_by_id(&region_ids)
+        .map_err(|error| HandlerError::database_error("Failed to query region name", error))?;
+    let mut result = regions.clone();
+    result.extend(extended_regions);
+
+    Ok(result)
+}
+
+pub fn collect_query_regions(
+    source: &mut dyn RegionSource,
+    query: &Query,
+) -> HandlerResult<HashMap<i64, DbRegion>> {
+    let leaf = query.leaf();
+    let mut result = HashMap::new();
+
+    for name in leaf.names() {
+        let regions = match name.mode() {
+            MatchMode::Regex => source.regions_by_regex(name.text()),
+            _ => source.regions_by_name(&name.sql_pattern()),
+        }
+        .map_err(|error| HandlerError::database_error("Failed to query region by name", error))?;
+
+        result.extend(regions);
+    }
+
+    // Regions found only by names in other languages does not match.
+    result.retain(|_, region| !region.names().is_empty() && !leaf.is_excluded(region));
+
+    Ok(result)
+}
+
+/// Finds all hierarchies matched to query. Returns matched hierarchies with names of all regions
+/// referenced by found hierarchies.
+pub fn find_query_hierarchies(
+    source: &mut dyn RegionSource,
+    query: &Query,
+) -> HandlerResult<FoundHierarchies> {
+    if query.filter().has_types() && !source.has_region_types() {
+        return Err(HandlerError::new(TYPE_ATTRIBUTE_NOT_DEFINED));
+    }
+
+    let query_regions = collect_query_regions(source, query)?;
+    let extended_hierarchies = collect_hierarchy(source, query_regions.keys().cloned())?;
+    let all_regions = collect_all_regions(source, &query_regions, &extended_hierarchies)?;
+
+    Ok(FoundHierarchies::new(
+        query,
+        all_regions,
+        &extended_hierarchies,
+    ))
+}
+
+/// Hierarchies matched to query with names of all regions referenced by candidate hierarchies.
+/// Strict query also reports parts of candidate hierarchies without loaded names, explained query
+/// keeps matched parts of every hierarchy and rejected candidates.
+#[derive(Debug)]
+pub struct FoundHierarchies {
+    regions: HashMap<i64, DbRegion>,
+    hierarchies: Vec<DbHierarchy>,
+    matches: Vec<Option<Vec<PartMatch>>>,
+    missing_parts: Option<Vec<i64>>,
+    rejected: Option<Vec<RejectedHierarchy>>,
+}
+
+impl FoundHierarchies {
+    fn new(
+        query: &Query,
+        regions: HashMap<i64, DbRegion>,
+        candidates: &[DbHierarchy],
+    ) -> FoundHierarchies {
+        let mut hierarchies = Vec::new();
+        let mut matches = Vec::new();
+        let mut rejected = None;
+
+        if query.is_explain() {
+            let mut rejected_hierarchies = Vec::new();
+
+            for hierarchy in candidates {
+                match query::explain_hierarchy(hierarchy, query, &regions) {
+                    Ok(part_matches) => {
+                        hierarchies.push(hierarchy.clone());
+                        matches.push(Some(part_matches));
+                    }
+                    Err(rejection) => {
+                        rejected_hierarchies.push(RejectedHierarchy::new(hierarchy, rejection))
+                    }
+                }
+            }
+
+            rejected = Some(rejected_hierarchies);
+        } else {
+            hierarchies = query::collect_query_hierarchies(query, &regions, candidates)
+                .into_iter()
+                .cloned()
+                .collect();
+            matches.resize(hierarchies.len(), None);
+        }
+
+        let missing_parts = if query.is_strict() {
+            Some(query::missing_parts(candidates, &regions))
+        } else {
+            None
+        };
+
+        FoundHierarchies {
+            regions,
+            hierarchies,
+            matches,
+            missing_parts,
+            rejected,
+        }
+    }
+
+    pub fn into_hierarchies(self) -> Vec<DbHierarchy> {
+        self.hierarchies
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Response {
+    regions: HashMap<i64, Region>,
+    hierarchies: Vec<Hierarchy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    total: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    missing_parts: Option<Vec<i64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rejected: Option<Vec<RejectedHierarchy>>,
+}
+
+impl Response {
+    pub fn new<'a, I>(regions: HashMap<i64, DbRegion>, hierarchies: I) -> Response
+    where
+        I: IntoIterator<Item = &'a DbHierarchy>,
+    {
+        Response {
+            regions: regions
+                .into_iter()
+                .map(|(id, region)| (id, region.into()))
+                .collect(),
+            hierarchies: hierarchies.into_iter().map(Hierarchy::from).collect(),
+            total: None,
+            missing_parts: None,
+            rejected: None,
+        }
+    }
+
+    /// Creates response with single page of found hierarchies ordered by identifiers. Regions map
+    /// contains only regions referenced by hierarchies of this page. Rejected hierarchies are
+    /// paged with the same offset and limit. Grouped response is paged by groups.
+    pub fn page(
+        found: FoundHierarchies,
+        offset: usize,
+        limit: usize,
+        grouping: Grouping,
+    ) -> Response {
+        let FoundHierarchies {
+            mut regions,
+            hierarchies,
+            mut matches,
+            missing_parts,
+            rejected,
+        } = found;
+        let mut order: Vec<_> = (0..hierarchies.len()).collect();
+
+        order.sort_by_key(|&index| {
+            let hierarchy = &hierarchies[index];
+
+            (hierarchy.id(), hierarchy.region_id(), hierarchy.parts())
+        });
+
+        let groups = match grouping {
+            Grouping::Flat => order.into_iter().map(|index| vec![index]).collect(),
+            Grouping::Region { .. } => group_by_region(&hierarchies, order),
+        };
+        let total = groups.len();
+        let page: Vec<_> = groups.into_iter().skip(offset).take(limit).collect();
+        let region_ids: HashSet<_> = page
+            .iter()
+            .flatten()
+            .map(|&index| &hierarchies[index])
+            .flat_map(|hierarchy| {
+                hierarchy
+                    .parts()
+                    .iter()
+                    .cloned()
+                    .chain(vec![hierarchy.id(), hierarchy.region_id()])
+            })
+            .collect();
+
+        regions.retain(|id, _| region_ids.contains(id));
+
+        let mut hierarchy = |index: usize| Hierarchy {
+            matches: matches[index].take(),
+            ..Hierarchy::from(&hierarchies[index])
+        };
+        let page = page
+            .into_iter()
+            .map(|group| {
+                let mut group = group.into_iter().map(&mut hierarchy);
+                let mut canonical = group.next().expect("Hierarchy group is empty");
+                let mut alternates: Vec<Hierarchy> = group.collect();
+
+                if grouping == (Grouping::Region { collapse: true }) {
+                    let count = alternates.len();
+                    let mut paths = vec![canonical.parts.clone()];
+
+                    alternates.retain(|alternate| {
+                        let is_unique = !paths.contains(&alternate.parts);
+
+                        if is_unique {
+                            paths.push(alternate.parts.clone());
+                        }
+
+                        is_unique
+                    });
+                    canonical.collapsed = Some(count - alternates.len()).filter(|&n| n > 0);
+                }
+
+                canonical.alternates = alternates;
+                canonical
+            })
+            .collect();
+
+        Response {
+            regions: regions
+                .into_iter()
+                .map(|(id, region)| (id, region.into()))
+                .collect(),
+            hierarchies: page,
+            total: Some(total),
+            missing_parts,
+            rejected: rejected.map(|mut rejected| {
+                rejected.sort_by_key(|hierarchy| (hierarchy.id, hierarchy.region_id));
+                rejected.into_iter().skip(offset).take(limit).collect()
+            }),
+        }
+    }
+}
+
+/// Groups hierarchy indices by region, groups are ordered by region identifier. The first
+/// hierarchy of group is canonical: hierarchy of the region itself (not bigger one) with the
+/// shortest path.
+fn group_by_region(hierarchies: &[DbHierarchy], order: Vec<usize>) -> Vec<Vec<usize>> {
+    let mut groups: BTreeMap<i64, Vec<usize>> = BTreeMap::new();
+
+    for index in order {
+        groups
+            .entry(hierarchies[index].region_id())
+            .or_default()
+            .push(index);
+    }
+
+    groups
+        .into_values()
+        .map(|mut group| {
+            // Stable sort keeps identifier order of equal hierarchies.
+            group.sort_by_key(|&index| {
+                let hierarchy = &hierarchies[index];
+
+                (
+                    hierarchy.id() != hierarchy.region_id(),
+                    hierarchy.parts().len(),
+                )
+            });
+            group
+        })
+        .collect()
+}
+
+/// Grouping of found hierarchies. Region grouping returns all hierarchies of the same region as
+/// single canonical hierarchy with nested alternates, alternates with the same path as canonical
+/// hierarchy or any earlier alternate of group can be collapsed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Grouping {
+    #[default]
+    Flat,
+    Region {
+        collapse: bool,
+    },
+}
+
+/// Candidate hierarchy which does not match query, `part` is set if hierarchy is rejected by
+/// query part.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RejectedHierarchy {
+    id: i64,
+    region_id: i64,
+    reason: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    part: Option<usize>,
+}
+
+impl RejectedHierarchy {
+    fn new(hierarchy: &DbHierarchy, rejection: Rejection) -> RejectedHierarchy {
+        RejectedHierarchy {
+            id: hierarchy.id(),
+            region_id: hierarchy.region_id(),
+            reason: rejection.to_string(),
+            part: rejection.part(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Region {
+    default_name: String,
+    names: HashMap<String, String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    attributes: BTreeMap<String, AttributeValue>,
+}
+
+impl From<DbRegion> for Region {
+    fn from(region: DbRegion) -> Region {
+        Region {
+            default_name: region.default_name().into(),
+            names: region
+                .names()
+                .iter()
+                .map(|name| (name.language().into(), name.name().into()))
+                .collect(),
+            attributes: region.attributes().clone(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Hierarchy {
+    id: i64,
+    region_id: i64,
+    parts: Vec<i64>,
+    bigger: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    matches: Option<Vec<PartMatch>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    alternates: Vec<Hierarchy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    collapsed: Option<usize>,
+}
+
+impl From<&DbHierarchy> for Hierarchy {
+    fn from(hierarchy: &DbHierarchy) -> Hierarchy {
+        let bigger = hierarchy.id() != hierarchy.region_id();
+
+        Hierarchy {
+            id: hierarchy.id(),
+            region_id: hierarchy.region_id(),
+            parts: hierarchy.parts().into(),
+            bigger,
+            matches: None,
+            alternates: Vec::new(),
+            collapsed: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::DatabaseResult;
+    use crate::database::LanguageFilter;
+    use crate::database::RegionRecord;
+
+    /// Region source over fixed regions, records requested patterns and identifiers. Names are
+    /// matched to `like` patterns with `%`, `_` and `\` escapes.
+    struct StubSource {
+        records: Vec<RegionRecord>,
+        hierarchies: Vec<DbHierarchy>,
+        filter: LanguageFilter,
+        has_types: bool,
+        patterns: Vec<String>,
+        requested_ids: Vec<i64>,
+    }
+
+    impl StubSource {
+        fn new(has_types: bool) -> StubSource {
+            let query_schema: QuerySchemaSettings = serde_yaml::from_str(if has_types {
+                "type_attribute: type"
+            } else {
+                "{}"
+            })
+            .unwrap();
+            let names = [
+                (1, "Britain"),
+                (2, "England"),
+                (3, "London"),
+                (4, "100% Park"),
+                (5, "Londonderry"),
+            ];
+
+            StubSource {
+                records: names
+                    .iter()
+                    .map(|&(id, name)| RegionRecord::new(id, "en", name, true, Vec::new()))
+                    .collect(),
+                hierarchies: vec![
+                    DbHierarchy::new(3, 3, &[Some(1), Some(2), Some(3)]),
+                    DbHierarchy::new(4, 4, &[Some(1), Some(2), Some(3), Some(4)]),
+                    DbHierarchy::new(5, 5, &[Some(1), Some(5)]),
+                ],
+                filter: LanguageFilter::new(&[], &query_schema),
+                has_types,
+                patterns: Vec::new(),
+                requested_ids: Vec::new(),
+            }
+        }
+
+        fn find<P>(&self, predicate: P) -> HashMap<i64, DbRegion>
+        where
+            P: Fn(&RegionRecord) -> bool,
+        {
+            let records: Vec<_> = self
+                .records
+                .iter()
+                .filter(|record| predicate(record))
+                .cloned()
+                .collect();
+
+            self.filter.build_regions(&records)
+        }
+    }
+
+    fn like_matches(pattern: &[char], text: &[char]) -> bool {
+        match (pattern.first(), text.first()) {
+            (None, _) => text.is_empty(),
+            (Some('%'), _) => {
+                (0..=text.len()).any(|skip| like_matches(&pattern[1..], &text[skip..]))
+            }
+            (Some('\\'), Some(ch)) => {
+                pattern.get(1) == Some(ch) && like_matches(&pattern[2..], &text[1..])
+            }
+            (Some('_'), Some(_)) => like_matches(&pattern[1..], &text[1..]),
+            (Some(expected), Some(ch)) => expected == ch && like_matches(&pattern[1..], &text[1..]),
+            (Some(_), None) => false,
+        }
+    }
+
+    impl RegionSource for StubSource {
+        fn set_languages(&mut self, languages: &[String]) {
+            let query_schema: QuerySchemaSettings = serde_yaml::from_str("{}").unwrap();
+
+            self.filter = LanguageFilter::new(languages, &query_schema);
+        }
+
+        fn has_region_types(&self) -> bool {
+            self.has_types
+        }
+
+        fn regions_by_name(&mut self, name: &str) -> DatabaseResult<HashMap<i64, DbRegion>> {
+            let pattern: Vec<_> = name.chars().collect();
+
+            self.patterns.push(name.into());
+
+            Ok(self.find(|record| {
+                let text: Vec<_> = record.name().to_lowercase().chars().collect();
+
+                like_matches(&pattern, &text)
+            }))
+        }
+
+        fn regions_by_regex(&mut self, pattern: &str) -> DatabaseResult<HashMap<i64, DbRegion>> {
+            let regex = regex::RegexBuilder::new(pattern)
+                .case_insensitive(true)
+                .build()
+                .unwrap();
+
+            self.patterns.push(pattern.into());
+
+            Ok(self.find(|record| regex.is_match(record.name())))
+        }
+
+        fn regions_by_id(&mut self, ids: &[i64]) -> DatabaseResult<HashMap<i64, DbRegion>> {
+            self.requested_ids.extend(ids);
+
+            Ok(self.find(|record| ids.contains(&record.id())))
+        }
+
+        fn hierarchy_by_id(&mut self, ids: &[i64]) -> DatabaseResult<Vec<DbHierarchy>> {
+            Ok(self
+                .hierarchies
+                .iter()
+                .filter(|hierarchy| ids.contains(&hierarchy.id()))
+                .cloned()
+                .collect())
+        }
+    }
+
+    fn region_ids(found: &FoundHierarchies) -> Vec<i64> {
+        let mut ids: Vec<_> = found
+            .hierarchies
+            .iter()
+            .map(DbHierarchy::region_id)
+            .collect();
+
+        ids.sort_unstable();
+        ids
+    }
+
+    fn find(source: &mut StubSource, query: &str, mode: MatchMode) -> FoundHierarchies {
+        let query = query::prepare_query(query, mode).unwrap();
+
+        find_query_hierarchies(source, &query).unwrap()
+    }
+
+    #[test]
+    fn find_by_leaf_pattern_and_loaded_parents() {
+        let mut source = StubSource::new(false);
+        let found = find(
+            &mut source,
+            "britain >> england > lon",
+            MatchMode::Substring,
+        );
+
+        assert_eq!(source.patterns, vec!["%lon%"]);
+        assert_eq!(region_ids(&found), vec![3]);
+
+        let mut requested_ids = source.requested_ids.clone();
+
+        requested_ids.sort_unstable();
+
+        assert_eq!(requested_ids, vec![1, 2]);
+        assert!(found.regions.contains_key(&1));
+    }
+
+    #[test]
+    fn find_with_alternatives_and_exclusions() {
+        let mut source = StubSource::new(false);
+        let found = find(&mut source, "london|park|!derry", MatchMode::Substring);
+
+        assert_eq!(source.patterns, vec!["%london%", "%park%"]);
+        assert_eq!(region_ids(&found), vec![3, 4]);
+
+        let mut source = StubSource::new(false);
+        let found = find(&mut source, "lon|!lond", MatchMode::Prefix);
+
+        assert_eq!(source.patterns, vec!["lon%"]);
+        assert!(region_ids(&found).is_empty());
+    }
+
+    #[test]
+    fn find_with_escaped_like_pattern() {
+        let mut source = StubSource::new(false);
+        let found = find(&mut source, "100%", MatchMode::Substring);
+
+        assert_eq!(source.patterns, vec!["%100\\%%"]);
+        assert_eq!(region_ids(&found), vec![4]);
+
+        let mut source = StubSource::new(false);
+        let found = find(&mut source, "l?nd*", MatchMode::Glob);
+
+        assert_eq!(source.patterns, vec!["l_nd%"]);
+        assert_eq!(region_ids(&found), vec![3, 5]);
+    }
+
+    #[test]
+    fn find_by_regex() {
+        let mut source = StubSource::new(false);
+        let found = find(&mut source, "england > ^lond", MatchMode::Regex);
+
+        assert_eq!(source.patterns, vec!["^lond"]);
+        assert_eq!(region_ids(&found), vec![3]);
+
+        let mut source = StubSource::new(false);
+        let found = find(&mut source, "^lond", MatchMode::Regex);
+
+        assert_eq!(source.patterns, vec!["^lond"]);
+        assert_eq!(region_ids(&found), vec![3, 5]);
+    }
+
+    #[test]
+    fn reject_region_types_without_type_attribute() {
+        let mut query = query::prepare_query("london", MatchMode::Substring).unwrap();
+
+        query
+            .set_filter(serde_json::from_str(r#"{ "types": ["city"] }"#).unwrap())
+            .unwrap();
+
+        assert!(find_query_hierarchies(&mut StubSource::new(false), &query).is_err());
+        assert!(find_query_hierarchies(&mut StubSource::new(true), &query).is_ok());
+    }
+
+    #[test]
+    fn collapse_alternate_with_not_adjacent_path() {
+        let found = FoundHierarchies {
+            regions: HashMap::new(),
+            hierarchies: vec![
+                DbHierarchy::new(23, 20, &[Some(1), Some(3), Some(20)]),
+                DbHierarchy::new(22, 20, &[Some(1), Some(4), Some(20)]),
+                DbHierarchy::new(21, 20, &[Some(1), Some(3), Some(20)]),
+                DbHierarchy::new(20, 20, &[Some(1), Some(20)]),
+            ],
+            matches: vec![None, None, None, None],
+            missing_parts: None,
+            rejected: None,
+        };
+        let response = Response::page(found, 0, 10, Grouping::Region { collapse: true });
+        let canonical = &response.hierarchies[0];
+        let alternates: Vec<_> = canonical
+            .alternates
+            .iter()
+            .map(|hierarchy| hierarchy.id)
+            .collect();
+
+        assert_eq!(response.hierarchies.len(), 1);
+        assert_eq!(canonical.id, 20);
+        assert_eq!(alternates, vec![21, 22]);
+        assert_eq!(canonical.collapsed, Some(1));
+    }
+}
@@ -1,5 +1,7 @@
+use super::common;
 use super::util::handle_empty;
 use crate::config::ConfigRef;
+use crate::config::ConnectionKind;
 use crate::config::ConnectionSettings;
 use crate::manager::DynamicConnectionsRef;
 use iron::middleware::Handler;
@@ -28,17 +30,11 @@ impl ConnectionsHandler {
 impl Handler for ConnectionsHandler {
     fn handle(&self, _req: &mut IromRequest) -> IronResult<IromResponse> {
         handle_empty(move || {
-            let mut connections: Vec<Connection> = self
-                .config
-                .connections()
-                .static_connections()
-                .iter()
-                .enumerate()
-                .map(|connection| connection.into())
-                .collect();
-            let _ = self
-                .dynamic_connections
-                .for_each(|index, connection| connections.push((index, connection).into()));
+            let mut connections: Vec<Connection> =
+                common::all_connections(&self.config, &self.dynamic_connections)
+                    .iter()
+                    .map(|(index, connection)| (*index, connection).into())
+                    .collect();
 
             connections.sort_by(|a, b| a.description.cmp(&b.description));
 
@@ -67,13 +63,23 @@ impl From<(usize, &ConnectionSettings)> for Connection {
     fn from(value: (usize, &ConnectionSettings)) -> Connection {
         let index = value.0;
         let connection = value.1;
-        let description = format!(
-            "{} ({}@{}/{})",
-            connection.description(),
-            connection.role(),
-            connection.host(),
-            connection.database()
-        );
+        let description = match connection.kind() {
+            ConnectionKind::Postgres => format!(
+                "{} ({}@{}/{})",
+                connection.description(),
+                connection.role(),
+                connection.host(),
+                connection.database()
+            ),
+            ConnectionKind::Sqlite => {
+                format!("{} ({})", connection.description(), connection.database())
+            }
+            ConnectionKind::File => format!(
+                "{} ({})",
+                connection.description(),
+                connection.names().unwrap_or_default()
+            ),
+        };
 
         Connection::new(index, &description)
     }
@@ -0,0 +1,508 @@
+use crate::cache::RegionCacheRef;
+use crate::config::ConfigRef;
+use crate::database::ClientPoolRef;
+use crate::database::Hierarchy as DbHierarchy;
+use crate::database::Region as DbRegion;
+use crate::database::RegionSource;
+use crate::handler::common;
+use crate::handler::common::Hierarchy;
+use crate::handler::common::Region;
+use crate::handler::error::HandlerError;
+use crate::handler::error::HandlerResult;
+use crate::handler::query;
+use crate::handler::query::MatchMode;
+use crate::handler::query::Query;
+use crate::handler::util::handle_request;
+use crate::index::RegionIndexRef;
+use crate::manager::DynamicConnectionsRef;
+use iron::middleware::Handler;
+use iron::IronResult;
+use iron::Request as IromRequest;
+use iron::Response as IromResponse;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::thread;
+
+type HierarchyKey = (i64, i64);
+
+#[derive(Debug)]
+pub struct DiffHandler {
+    config: ConfigRef,
+    dynamic_connections: DynamicConnectionsRef,
+    region_cache: RegionCacheRef,
+    client_pool: ClientPoolRef,
+    region_index: RegionIndexRef,
+}
+
+impl DiffHandler {
+    pub fn new(
+        config: ConfigRef,
+        dynamic_connections: DynamicConnectionsRef,
+        region_cache: RegionCacheRef,
+        client_pool: ClientPoolRef,
+        region_index: RegionIndexRef,
+    ) -> DiffHandler {
+        DiffHandler {
+            config,
+            dynamic_connections,
+            region_cache,
+            client_pool,
+            region_index,
+        }
+    }
+
+    fn prepare_selection(&self, request: &Request) -> HandlerResult<Selection> {
+        match (&request.query, &request.ids) {
+            (Some(query), None) => Ok(Selection::Query(query::prepare_query(
+                query,
+                request.match_mode,
+            )?)),
+            (None, Some(ids)) if !ids.is_empty() => Ok(Selection::Ids(ids.clone())),
+            (None, Some(_)) => Err(HandlerError::new("Region id list must not be empty")),
+            _ => Err(HandlerError::new(
+                "Either region query or region id list must be given",
+            )),
+        }
+    }
+
+    /// Runs function for left and right connection in parallel.
+    fn both_sides<T, F>(&self, request: &Request, f: F) -> HandlerResult<(T, T)>
+    where
+        T: Send,
+        F: Fn(usize) -> HandlerResult<T> + Sync,
+    {
+        let f = &f;
+        let (left, right) = thread::scope(|scope| {
+            let left = scope.spawn(|| f(request.left));
+            let right = scope.spawn(|| f(request.right));
+
+            (left.join(), right.join())
+        });
+        let left = left.unwrap_or_else(|_| Err(HandlerError::new("Diff thread panicked")))?;
+        let right = right.unwrap_or_else(|_| Err(HandlerError::new("Diff thread panicked")))?;
+
+        Ok((left, right))
+    }
+
+    fn region_source(
+        &self,
+        index: usize,
+        languages: &[String],
+    ) -> HandlerResult<Box<dyn RegionSource + '_>> {
+        let mut source = common::region_source(
+            &self.config,
+            &self.dynamic_connections,
+            &self.region_cache,
+            &self.client_pool,
+            &self.region_index,
+            index,
+        )?;
+
+        source.set_languages(languages);
+
+        Ok(source)
+    }
+
+    /// Finds identifiers of hierarchies matching query in given connection.
+    fn find_ids(
+        &self,
+        index: usize,
+        query: &Query,
+        languages: &[String],
+    ) -> HandlerResult<BTreeSet<i64>> {
+        let mut source = self.region_source(index, languages)?;
+        let hierarchies = common::find_query_hierarchies(source.as_mut(), query)?;
+
+        Ok(hierarchies
+            .into_hierarchies()
+            .iter()
+            .map(DbHierarchy::id)
+            .collect())
+    }
+
+    /// Loads hierarchies with given identifiers from given connection. Region names are always
+    /// loaded by id, because names found by query contain only matched names.
+    fn load_side(&self, index: usize, ids: &[i64], languages: &[String]) -> HandlerResult<Side> {
+        let mut source = self.region_source(index, languages)?;
+        let hierarchies = common::collect_hierarchy(source.as_mut(), ids.iter().cloned())?;
+        let regions = common::collect_all_regions(source.as_mut(), &HashMap::new(), &hierarchies)?;
+
+        Ok(Side::new(regions, hierarchies))
+    }
+}
+
+/// Compares hierarchies and region names of both sides. Region may have several hierarchies with
+/// the same identifiers, so paths are compared as sets. If exactly one path of hierarchy differs
+/// on each side, hierarchy is reported as changed, otherwise differing paths are reported as
+/// present only in one connection.
+fn diff(left: Side, right: Side) -> Response {
+    let mut only_left = Vec::new();
+    let mut only_right = Vec::new();
+    let mut changed_hierarchies = Vec::new();
+    let keys: BTreeSet<_> = left
+        .hierarchies
+        .keys()
+        .chain(right.hierarchies.keys())
+        .collect();
+    let empty = BTreeMap::new();
+
+    for key in keys {
+        let left_paths = left.hierarchies.get(key).unwrap_or(&empty);
+        let right_paths = right.hierarchies.get(key).unwrap_or(&empty);
+        let left_only: Vec<_> = left_paths
+            .iter()
+            .filter(|(parts, _)| !right_paths.contains_key(*parts))
+            .map(|(_, hierarchy)| hierarchy)
+            .collect();
+        let right_only: Vec<_> = right_paths
+            .iter()
+            .filter(|(parts, _)| !left_paths.contains_key(*parts))
+            .map(|(_, hierarchy)| hierarchy)
+            .collect();
+
+        match (left_only.as_slice(), right_only.as_slice()) {
+            ([left_hierarchy], [right_hierarchy]) => {
+                changed_hierarchies.push(ChangedHierarchy::new(left_hierarchy, right_hierarchy))
+            }
+            _ => {
+                only_left.extend(left_only.into_iter().map(Hierarchy::from));
+                only_right.extend(right_only.into_iter().map(Hierarchy::from));
+            }
+        }
+    }
+
+    let region_ids: BTreeSet<_> = left.regions.keys().chain(right.regions.keys()).collect();
+    let mut changed_names = Vec::new();
+
+    for &region_id in region_ids {
+        let left_names = names_by_language(left.regions.get(&region_id));
+        let right_names = names_by_language(right.regions.get(&region_id));
+        let languages: BTreeSet<_> = left_names.keys().chain(right_names.keys()).collect();
+
+        for language in languages {
+            let left_name = left_names.get(language);
+            let right_name = right_names.get(language);
+
+            if left_name != right_name {
+                changed_names.push(ChangedName {
+                    region_id,
+                    language: language.to_string(),
+                    left: left_name.cloned().unwrap_or_default(),
+                    right: right_name.cloned().unwrap_or_default(),
+                });
+            }
+        }
+    }
+
+    Response {
+        left_regions: convert_regions(left.regions),
+        right_regions: convert_regions(right.regions),
+        only_left,
+        only_right,
+        changed_hierarchies,
+        changed_names,
+    }
+}
+
+impl Handler for DiffHandler {
+    fn handle(&self, request: &mut IromRequest) -> IronResult<IromResponse> {
+        handle_request(request, move |request: Request| {
+            let selection = self.prepare_selection(&request)?;
+            let languages = &request.languages;
+            // Query is resolved in both connections first, so region renamed or moved in one of
+            // them is still compared with the other connection.
+            let ids: Vec<_> = match selection {
+                Selection::Query(query) => {
+                    let (left, right) =
+                        self.both_sides(&request, |index| self.find_ids(index, &query, languages))?;
+
+                    left.union(&right).cloned().collect()
+                }
+                Selection::Ids(ids) => ids,
+            };
+            let (left, right) =
+                self.both_sides(&request, |index| self.load_side(index, &ids, languages))?;
+
+            Ok(diff(left, right))
+        })
+    }
+}
+
+fn names_by_language(region: Option<&DbRegion>) -> BTreeMap<&str, Vec<String>> {
+    let mut result: BTreeMap<_, Vec<_>> = BTreeMap::new();
+
+    if let Some(region) = region {
+        for name in region.names() {
+            result
+                .entry(name.language())
+                .or_default()
+                .push(name.name().into());
+        }
+    }
+
+    for names in result.values_mut() {
+        names.sort();
+    }
+
+    result
+}
+
+fn convert_regions(regions: HashMap<i64, DbRegion>) -> HashMap<i64, Region> {
+    regions
+        .into_iter()
+        .map(|(id, region)| (id, region.into()))
+        .collect()
+}
+
+#[derive(Debug)]
+enum Selection {
+    Query(Query),
+    Ids(Vec<i64>),
+}
+
+/// Regions and hierarchies of one connection, hierarchies are grouped by identifiers and parts.
+#[derive(Debug)]
+struct Side {
+    regions: HashMap<i64, DbRegion>,
+    hierarchies: BTreeMap<HierarchyKey, BTreeMap<Vec<i64>, DbHierarchy>>,
+}
+
+impl Side {
+    fn new(regions: HashMap<i64, DbRegion>, hierarchies: Vec<DbHierarchy>) -> Side {
+        let mut grouped: BTreeMap<_, BTreeMap<_, _>> = BTreeMap::new();
+
+        for hierarchy in hierarchies {
+            grouped
+                .entry((hierarchy.id(), hierarchy.region_id()))
+                .or_default()
+                .insert(hierarchy.parts().to_vec(), hierarchy);
+        }
+
+        Side {
+            regions,
+            hierarchies: grouped,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Request {
+    left: usize,
+    right: usize,
+    query: Option<String>,
+    ids: Option<Vec<i64>>,
+    #[serde(default)]
+    match_mode: MatchMode,
+    #[serde(default)]
+    languages: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Response {
+    left_regions: HashMap<i64, Region>,
+    right_regions: HashMap<i64, Region>,
+    only_left: Vec<Hierarchy>,
+    only_right: Vec<Hierarchy>,
+    changed_hierarchies: Vec<ChangedHierarchy>,
+    changed_names: Vec<ChangedName>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ChangedHierarchy {
+    id: i64,
+    region_id: i64,
+    left_parts: Vec<i64>,
+    right_parts: Vec<i64>,
+}
+
+impl ChangedHierarchy {
+    fn new(left: &DbHierarchy, right: &DbHierarchy) -> ChangedHierarchy {
+        ChangedHierarchy {
+            id: left.id(),
+            region_id: left.region_id(),
+            left_parts: left.parts().into(),
+            right_parts: right.parts().into(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ChangedName {
+    region_id: i64,
+    language: String,
+    left: Vec<String>,
+    right: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache;
+    use crate::config::Config;
+    use crate::database;
+    use crate::index;
+    use crate::manager;
+    use iron::Iron;
+    use serde_json::json;
+    use serde_json::Value;
+    use std::env;
+    use std::fs;
+    use std::io::Read;
+    use std::io::Write;
+    use std::net::TcpStream;
+    use std::sync::Arc;
+
+    const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+
+    /// Creates handler with fixture connection on the left and connection with given region
+    /// names on the right.
+    fn handler(right_names: &str) -> DiffHandler {
+        let yaml = format!(
+            r#"
+connections:
+  static_connections:
+    - description: Left
+      query_schema: files
+      kind: file
+      names: {fixtures}/names.csv
+      hierarchies: {fixtures}/hierarchies.jsonl
+    - description: Right
+      query_schema: files
+      kind: file
+      names: {right_names}
+      hierarchies: {fixtures}/hierarchies.jsonl
+query_schemas:
+  files:
+    dialect: file
+    type_attribute: type
+"#,
+            fixtures = FIXTURES,
+            right_names = right_names
+        );
+        let config: ConfigRef = Arc::new(serde_yaml::from_str::<Config>(&yaml).unwrap());
+        let region_cache = cache::region_cache(&config);
+        let client_pool = database::client_pool();
+        let region_index = index::region_index();
+
+        index::start(&config, &region_cache, &client_pool, &region_index).unwrap();
+
+        DiffHandler::new(
+            config.clone(),
+            manager::dynamic_connections(&config),
+            region_cache,
+            client_pool,
+            region_index,
+        )
+    }
+
+    /// Sends request to handler served on random port and returns parsed response body.
+    fn send(handler: DiffHandler, request: &str) -> Value {
+        let mut listening = Iron::new(handler).http("127.0.0.1:0").unwrap();
+        let mut stream = TcpStream::connect(listening.socket).unwrap();
+
+        write!(
+            stream,
+            "POST / HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            request.len(),
+            request
+        )
+        .unwrap();
+
+        let mut response = String::new();
+
+        stream.read_to_string(&mut response).unwrap();
+        listening.close().unwrap();
+
+        let (_, body) = response.split_once("\r\n\r\n").unwrap();
+
+        serde_json::from_str(body).unwrap()
+    }
+
+    fn side(hierarchies: &[(i64, &[i64])]) -> Side {
+        let hierarchies = hierarchies
+            .iter()
+            .map(|(id, parts)| {
+                let levels: Vec<_> = parts.iter().cloned().map(Some).collect();
+
+                DbHierarchy::new(*id, *id, &levels)
+            })
+            .collect();
+
+        Side::new(HashMap::new(), hierarchies)
+    }
+
+    fn paths(response: &serde_json::Value, field: &str) -> serde_json::Value {
+        response[field]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|hierarchy| hierarchy["parts"].clone())
+            .collect()
+    }
+
+    #[test]
+    fn diff_alternate_paths_as_sets() {
+        let left = side(&[(11, &[1, 11]), (11, &[1, 2, 11])]);
+        let right = side(&[(11, &[1, 2, 11]), (11, &[1, 11])]);
+        let response = serde_json::to_value(diff(left, right)).unwrap();
+
+        assert_eq!(paths(&response, "onlyLeft"), json!([]));
+        assert_eq!(paths(&response, "onlyRight"), json!([]));
+        assert_eq!(response["changedHierarchies"], json!([]));
+
+        let left = side(&[(11, &[1, 11]), (11, &[1, 2, 11]), (3, &[1, 3])]);
+        let right = side(&[(11, &[1, 2, 11]), (11, &[1, 5, 11]), (11, &[1, 7, 11])]);
+        let response = serde_json::to_value(diff(left, right)).unwrap();
+
+        assert_eq!(paths(&response, "onlyLeft"), json!([[1, 3], [1, 11]]));
+        assert_eq!(
+            paths(&response, "onlyRight"),
+            json!([[1, 5, 11], [1, 7, 11]])
+        );
+        assert_eq!(response["changedHierarchies"], json!([]));
+    }
+
+    #[test]
+    fn diff_changed_path() {
+        let left = side(&[(11, &[1, 11]), (11, &[1, 2, 11])]);
+        let right = side(&[(11, &[1, 2, 11]), (11, &[1, 5, 11])]);
+        let response = serde_json::to_value(diff(left, right)).unwrap();
+
+        assert_eq!(paths(&response, "onlyLeft"), json!([]));
+        assert_eq!(paths(&response, "onlyRight"), json!([]));
+        assert_eq!(
+            response["changedHierarchies"],
+            json!([{ "id": 11, "regionId": 11, "leftParts": [1, 11], "rightParts": [1, 5, 11] }])
+        );
+    }
+
+    #[test]
+    fn diff_query_region_renamed_on_one_side() {
+        let names = fs::read_to_string(format!("{}/names.csv", FIXTURES)).unwrap();
+        let path = env::temp_dir().join(format!("find_region_diff_{}.csv", std::process::id()));
+
+        fs::write(&path, names.replace("3,en,London,", "3,en,Londinium,")).unwrap();
+
+        let request = r#"{ "left": 0, "right": 1, "query": "kingdom >> london" }"#;
+        let response = send(handler(&path.display().to_string()), request);
+
+        fs::remove_file(&path).unwrap();
+
+        let result = &response["result"];
+
+        assert_eq!(response["success"], true);
+        assert_eq!(paths(result, "onlyLeft"), json!([]));
+        assert_eq!(paths(result, "onlyRight"), json!([]));
+        assert_eq!(
+            result["changedNames"],
+            json!([{ "regionId": 3, "language": "en", "left": ["London"], "right": ["Londinium"] }])
+        );
+        assert_eq!(result["rightRegions"]["3"]["defaultName"], "Londinium");
+    }
+}
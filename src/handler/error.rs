@@ -1,3 +1,4 @@
+use crate::database::DatabaseError;
 use std::error::Error;
 use std::fmt::Display;
 use std::fmt::Formatter;
@@ -8,14 +9,47 @@ pub type HandlerResult<T> = Result<T, HandlerError>;
 #[derive(Debug)]
 pub struct HandlerError {
     message: String,
+    position: Option<usize>,
+    timeout: bool,
 }
 
 impl HandlerError {
     pub fn new(message: &str) -> HandlerError {
         HandlerError {
             message: message.into(),
+            position: None,
+            timeout: false,
         }
     }
+
+    pub fn syntax_error(message: &str, position: usize) -> HandlerError {
+        HandlerError {
+            message: format!("{} at position {}", message, position),
+            position: Some(position),
+            timeout: false,
+        }
+    }
+
+    pub fn database_error(message: &str, error: DatabaseError) -> HandlerError {
+        match error {
+            DatabaseError::QueryNotDefinedError { .. }
+            | DatabaseError::MissingColumnError { .. } => HandlerError::new(&format!("{}", error)),
+            DatabaseError::QueryTimeoutError { .. } => HandlerError {
+                message: format!("{}", error),
+                position: None,
+                timeout: true,
+            },
+            _ => HandlerError::new(message),
+        }
+    }
+
+    pub fn position(&self) -> Option<usize> {
+        self.position
+    }
+
+    pub fn is_timeout(&self) -> bool {
+        self.timeout
+    }
 }
 
 impl Error for HandlerError {}
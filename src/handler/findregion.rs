@@ -1,257 +1,587 @@
+use crate::cache::RegionCacheRef;
 use crate::config::ConfigRef;
-use crate::database::Database;
-use crate::database::DatabaseClient;
-use crate::database::Hierarchy as DbHierarchy;
-use crate::database::Region as DbRegion;
+use crate::config::ConnectionSettings;
+use crate::database::ClientPoolRef;
+use crate::handler::common;
+use crate::handler::common::FoundHierarchies;
+use crate::handler::common::Grouping;
+use crate::handler::common::Response;
 use crate::handler::error::HandlerError;
 use crate::handler::error::HandlerResult;
+use crate::handler::query;
+use crate::handler::query::LeafFilter;
+use crate::handler::query::MatchMode;
+use crate::handler::query::Query;
 use crate::handler::util::handle_request;
+use crate::index::RegionIndexRef;
 use crate::manager::DynamicConnectionsRef;
 use iron::middleware::Handler;
 use iron::IronResult;
 use iron::Request as IromRequest;
 use iron::Response as IromResponse;
-use std::collections::HashMap;
-use std::collections::HashSet;
+use std::thread;
+
+/// Maximal number of hierarchies returned by single search if connection does not define limit.
+const DEFAULT_MAX_RESULTS: usize = 1000;
 
 #[derive(Debug)]
 pub struct FindRegionHandler {
     config: ConfigRef,
     dynamic_connections: DynamicConnectionsRef,
+    region_cache: RegionCacheRef,
+    client_pool: ClientPoolRef,
+    region_index: RegionIndexRef,
 }
 
-const QUERY_SEPARATOR: char = '>';
-
 impl FindRegionHandler {
-    pub fn new(config: ConfigRef, dynamic_connections: DynamicConnectionsRef) -> FindRegionHandler {
+    pub fn new(
+        config: ConfigRef,
+        dynamic_connections: DynamicConnectionsRef,
+        region_cache: RegionCacheRef,
+        client_pool: ClientPoolRef,
+        region_index: RegionIndexRef,
+    ) -> FindRegionHandler {
         FindRegionHandler {
             config,
             dynamic_connections,
+            region_cache,
+            client_pool,
+            region_index,
         }
     }
 
-    fn prepare_query(&self, query: String) -> HandlerResult<(String, Vec<String>)> {
-        if query.is_empty() {
-            return Err(HandlerError::new("Region name query must not be empty"));
-        }
+    fn find_regions(
+        &self,
+        index: usize,
+        query: &Query,
+        languages: &[String],
+        page: &Page,
+    ) -> HandlerResult<Response> {
+        let mut source = common::region_source(
+            &self.config,
+            &self.dynamic_connections,
+            &self.region_cache,
+            &self.client_pool,
+            &self.region_index,
+            index,
+        )?;
 
-        let query_parts: Vec<_> = query
-            .split(QUERY_SEPARATOR)
-            .map(|name| name.trim().to_lowercase())
-            .collect();
+        source.set_languages(languages);
 
-        if query_parts.iter().any(|name| name.is_empty()) {
-            return Err(HandlerError::new("Region name must not be empty"));
-        }
+        let found = common::find_query_hierarchies(source.as_mut(), query)?;
+        let connection =
+            common::connection_settings(&self.config, &self.dynamic_connections, index)?;
 
-        match query_parts.last() {
-            Some(last) if last.chars().any(|ch| ch.is_alphanumeric()) => {
-                Ok((last.into(), query_parts))
-            }
-            Some(_) => Err(HandlerError::new(
-                "Region name must contains at least one letter or digit",
-            )),
-            None => Err(HandlerError::new("Query must contain at least one part")),
-        }
+        Ok(page.response(&connection, found))
     }
 
-    fn prepare_connection(&self, index: usize) -> HandlerResult<DatabaseClient> {
-        let dynamic_connection = self.dynamic_connections.get(index).unwrap_or(None);
-        let static_connection = self.config.connections().static_connections().get(index);
-        let connection = match static_connection.cloned().or(dynamic_connection) {
-            Some(connection) => connection,
-            None => {
-                return Err(HandlerError::new(&format!(
-                    "Invalid connection index `{}`",
-                    index
-                )))
-            }
-        };
-        let query_schema = match self.config.query_schemas().get(connection.query_schema()) {
-            Some(query_schema) => query_schema,
-            None => {
-                return Err(HandlerError::new(&format!(
-                    "Invalid query schema `{}` in connection `{}`",
-                    connection.query_schema(),
-                    connection.description(),
-                )))
-            }
-        };
+    /// Runs search on every connection in separate thread. Errors are reported per connection, so
+    /// single failed connection does not affect other results.
+    fn find_regions_parallel(
+        &self,
+        indices: &[usize],
+        query: &Query,
+        languages: &[String],
+        page: &Page,
+    ) -> Vec<ConnectionResponse> {
+        thread::scope(|scope| {
+            let handles: Vec<_> = indices
+                .iter()
+                .map(|&index| {
+                    (
+                        index,
+                        scope.spawn(move || self.find_regions(index, query, languages, page)),
+                    )
+                })
+                .collect();
 
-        Database::new(connection, query_schema)
-            .connect()
-            .map_err(|_| HandlerError::new("Failed to connect to database"))
-    }
+            handles
+                .into_iter()
+                .map(|(index, handle)| {
+                    let description =
+                        common::connection_settings(&self.config, &self.dynamic_connections, index)
+                            .map(|connection| connection.description().to_string())
+                            .unwrap_or_default();
+                    let result = handle
+                        .join()
+                        .unwrap_or_else(|_| Err(HandlerError::new("Search thread panicked")));
 
-    fn collect_hierarchy<I>(
-        &self,
-        client: &mut DatabaseClient,
-        it: I,
-    ) -> HandlerResult<Vec<DbHierarchy>>
-    where
-        I: IntoIterator<Item = i64>,
-    {
-        client
-            .hierarchy_by_id(it)
-            .map_err(|_| HandlerError::new("Failed to query hierarchy"))
+                    ConnectionResponse::new(index, &description, result)
+                })
+                .collect()
+        })
     }
+}
 
-    fn collect_all_regions(
-        &self,
-        client: &mut DatabaseClient,
-        regions: &HashMap<i64, DbRegion>,
-        hierarchies: &[DbHierarchy],
-    ) -> HandlerResult<HashMap<i64, DbRegion>> {
-        let mut region_ids = HashSet::new();
-
-        for hierarchy in hierarchies {
-            region_ids.insert(hierarchy.id());
-            region_ids.extend(hierarchy.parts());
-        }
+impl Handler for FindRegionHandler {
+    fn handle(&self, request: &mut IromRequest) -> IronResult<IromResponse> {
+        handle_request(request, move |request: Request| {
+            let mut query = query::prepare_query(&request.query, request.match_mode)?;
 
-        let extended_regions = client
-            .regions_by_id(
-                region_ids
-                    .into_iter()
-                    .filter(|region_id| !regions.contains_key(region_id)),
-            )
-            .map_err(|_| HandlerError::new("Failed to query region name"))?;
-        let mut result = regions.clone();
-        result.extend(extended_regions);
-
-        Ok(result)
-    }
+            query.set_filter(request.filter)?;
+            query.set_strict(request.strict);
+            query.set_explain(request.explain);
 
-    fn collect_query_hierarchies<'a>(
-        &self,
-        query_parts: &[String],
-        regions: &HashMap<i64, DbRegion>,
-        hierarchies: &'a [DbHierarchy],
-    ) -> Vec<&'a DbHierarchy> {
-        hierarchies
-            .iter()
-            .filter(|hierarchy| self.is_hierarchy_matches(hierarchy, query_parts, regions))
-            .collect()
-    }
+            let page = Page::new(
+                request.offset,
+                request.limit,
+                request.group,
+                request.collapse,
+            )?;
 
-    fn is_hierarchy_matches(
-        &self,
-        hierarchy: &DbHierarchy,
-        query_parts: &[String],
-        regions: &HashMap<i64, DbRegion>,
-    ) -> bool {
-        let hierarchy_parts = hierarchy.parts();
-        let mut hierarchy_index = 0;
-
-        // Last part is required region and should be skipped.
-        for query_part in &query_parts[0..query_parts.len() - 1] {
-            loop {
-                match hierarchy_parts
-                    .get(hierarchy_index)
-                    .and_then(|index| regions.get(index))
-                {
-                    // If region name is present in query - skip this region and get next query part.
-                    Some(region) if region.contains_name(query_part) => {
-                        hierarchy_index += 1;
-
-                        break;
-                    }
-                    // If region name not in query - skip this region and continue search.
-                    Some(_) => hierarchy_index += 1,
-                    // If this region not loaded - accept this hierarchy to avoid false positive case.
-                    None => return true,
+            match request.connection {
+                ConnectionSelector::Single(index) => self
+                    .find_regions(index, &query, &request.languages, &page)
+                    .map(FindResponse::Single),
+                ConnectionSelector::List(ref indices) if indices.is_empty() => {
+                    Err(HandlerError::new("Connection list must not be empty"))
                 }
+                ConnectionSelector::List(ref indices) => Ok(FindResponse::Multiple {
+                    connections: self.find_regions_parallel(
+                        indices,
+                        &query,
+                        &request.languages,
+                        &page,
+                    ),
+                }),
+                ConnectionSelector::Schema { ref schema } => {
+                    let indices: Vec<_> =
+                        common::all_connections(&self.config, &self.dynamic_connections)
+                            .into_iter()
+                            .filter(|(_, connection)| connection.query_schema() == schema)
+                            .map(|(index, _)| index)
+                            .collect();
+
+                    if indices.is_empty() {
+                        return Err(HandlerError::new(&format!(
+                            "No connections with query schema `{}`",
+                            schema
+                        )));
+                    }
 
-                if hierarchy_index >= hierarchy_parts.len() {
-                    return false;
+                    Ok(FindResponse::Multiple {
+                        connections: self.find_regions_parallel(
+                            &indices,
+                            &query,
+                            &request.languages,
+                            &page,
+                        ),
+                    })
                 }
             }
-        }
-
-        true
+        })
     }
 }
 
-impl Handler for FindRegionHandler {
-    fn handle(&self, request: &mut IromRequest) -> IronResult<IromResponse> {
-        handle_request(request, move |request: Request| {
-            let (name, query_parts) = self.prepare_query(request.query)?;
-            let mut client = self.prepare_connection(request.connection)?;
-            let query_regions = client
-                .regions_by_name(&name)
-                .map_err(|_| HandlerError::new("Failed to query region by name"))?;
-            let extended_hierarchies =
-                self.collect_hierarchy(&mut client, query_regions.keys().cloned())?;
-            let all_regions =
-                self.collect_all_regions(&mut client, &query_regions, &extended_hierarchies)?;
-            let query_hierarchies =
-                self.collect_query_hierarchies(&query_parts, &all_regions, &extended_hierarchies);
-            let regions = all_regions
-                .into_iter()
-                .map(|(id, region)| (id, region.into()))
-                .collect();
-            let hierarchies = query_hierarchies.into_iter().map(Hierarchy::from).collect();
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Request {
+    connection: ConnectionSelector,
+    query: String,
+    #[serde(default)]
+    match_mode: MatchMode,
+    #[serde(default)]
+    languages: Vec<String>,
+    #[serde(default)]
+    filter: LeafFilter,
+    #[serde(default)]
+    strict: bool,
+    #[serde(default)]
+    explain: bool,
+    #[serde(default)]
+    group: bool,
+    #[serde(default)]
+    collapse: bool,
+    #[serde(default)]
+    offset: usize,
+    limit: Option<usize>,
+}
+
+/// Requested page of found hierarchies. Page size is limited by connection maximal number of
+/// results.
+#[derive(Debug)]
+struct Page {
+    offset: usize,
+    limit: Option<usize>,
+    grouping: Grouping,
+}
+
+impl Page {
+    fn new(
+        offset: usize,
+        limit: Option<usize>,
+        group: bool,
+        collapse: bool,
+    ) -> HandlerResult<Page> {
+        if limit == Some(0) {
+            return Err(HandlerError::new("Limit must be greater than zero"));
+        }
 
-            Ok(Response {
-                regions,
-                hierarchies,
-            })
+        let grouping = match (group, collapse) {
+            (false, false) => Grouping::Flat,
+            (false, true) => {
+                return Err(HandlerError::new(
+                    "Identical paths can be collapsed only in grouped response",
+                ))
+            }
+            (true, collapse) => Grouping::Region { collapse },
+        };
+
+        Ok(Page {
+            offset,
+            limit,
+            grouping,
         })
     }
+
+    fn response(&self, connection: &ConnectionSettings, found: FoundHierarchies) -> Response {
+        let max_results = connection.max_results().unwrap_or(DEFAULT_MAX_RESULTS);
+        let limit = self
+            .limit
+            .map_or(max_results, |limit| limit.min(max_results));
+
+        Response::page(found, self.offset, limit, self.grouping)
+    }
 }
 
+/// Connection can be selected by index, by list of indices or by query schema name. In the last
+/// case all connections with given query schema are used.
 #[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct Request {
-    connection: usize,
-    query: String,
+#[serde(untagged)]
+enum ConnectionSelector {
+    Single(usize),
+    List(Vec<usize>),
+    Schema { schema: String },
 }
 
 #[derive(Debug, Serialize)]
-#[serde(rename_all = "camelCase")]
-struct Response {
-    regions: HashMap<i64, Region>,
-    hierarchies: Vec<Hierarchy>,
+#[serde(untagged)]
+enum FindResponse {
+    Single(Response),
+    Multiple {
+        connections: Vec<ConnectionResponse>,
+    },
 }
 
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
-struct Region {
-    default_name: String,
-    names: HashMap<String, String>,
+struct ConnectionResponse {
+    connection: usize,
+    description: String,
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Response>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
 }
 
-impl From<DbRegion> for Region {
-    fn from(region: DbRegion) -> Region {
-        Region {
-            default_name: region.default_name().into(),
-            names: region
-                .names()
-                .iter()
-                .map(|name| (name.language().into(), name.name().into()))
-                .collect(),
+impl ConnectionResponse {
+    fn new(
+        connection: usize,
+        description: &str,
+        result: HandlerResult<Response>,
+    ) -> ConnectionResponse {
+        match result {
+            Ok(result) => ConnectionResponse {
+                connection,
+                description: description.into(),
+                success: true,
+                result: Some(result),
+                message: None,
+            },
+            Err(error) => ConnectionResponse {
+                connection,
+                description: description.into(),
+                success: false,
+                result: None,
+                message: Some(format!("{}", error)),
+            },
         }
     }
 }
 
-#[derive(Debug, Serialize)]
-#[serde(rename_all = "camelCase")]
-struct Hierarchy {
-    id: i64,
-    region_id: i64,
-    parts: Vec<i64>,
-    bigger: bool,
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache;
+    use crate::config::Config;
+    use crate::database;
+    use crate::index;
+    use crate::manager;
+    use iron::Iron;
+    use serde_json::json;
+    use serde_json::Value;
+    use std::io::Read;
+    use std::io::Write;
+    use std::net::TcpStream;
+    use std::sync::Arc;
+
+    const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
 
-impl From<&DbHierarchy> for Hierarchy {
-    fn from(hierarchy: &DbHierarchy) -> Hierarchy {
-        let bigger = hierarchy.id() != hierarchy.region_id();
+    fn handler() -> FindRegionHandler {
+        let yaml = format!(
+            r#"
+connections:
+  static_connections:
+    - description: Fixtures
+      query_schema: files
+      kind: file
+      names: {fixtures}/names.csv
+      hierarchies: {fixtures}/hierarchies.jsonl
+      max_results: 3
+query_schemas:
+  files:
+    dialect: file
+    type_attribute: type
+"#,
+            fixtures = FIXTURES
+        );
+        let config: ConfigRef = Arc::new(serde_yaml::from_str::<Config>(&yaml).unwrap());
+        let region_cache = cache::region_cache(&config);
+        let client_pool = database::client_pool();
+        let region_index = index::region_index();
 
-        Hierarchy {
-            id: hierarchy.id(),
-            region_id: hierarchy.region_id(),
-            parts: hierarchy.parts().into(),
-            bigger,
+        index::start(&config, &region_cache, &client_pool, &region_index).unwrap();
+
+        FindRegionHandler::new(
+            config.clone(),
+            manager::dynamic_connections(&config),
+            region_cache,
+            client_pool,
+            region_index,
+        )
+    }
+
+    /// Sends request to handler served on random port and returns parsed response body.
+    fn find(request: &str) -> Value {
+        let mut listening = Iron::new(handler()).http("127.0.0.1:0").unwrap();
+        let mut stream = TcpStream::connect(listening.socket).unwrap();
+
+        write!(
+            stream,
+            "POST / HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            request.len(),
+            request
+        )
+        .unwrap();
+
+        let mut response = String::new();
+
+        stream.read_to_string(&mut response).unwrap();
+        listening.close().unwrap();
+
+        let (_, body) = response.split_once("\r\n\r\n").unwrap();
+
+        serde_json::from_str(body).unwrap()
+    }
+
+    fn region_ids(response: &Value) -> Vec<i64> {
+        response["result"]["hierarchies"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|hierarchy| hierarchy["regionId"].as_i64().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn find_hierarchies_by_parent_names() {
+        let response = find(r#"{ "connection": 0, "query": "kingdom >> london" }"#);
+
+        assert_eq!(response["success"], true);
+        assert_eq!(region_ids(&response), vec![3]);
+        assert_eq!(
+            response["result"]["hierarchies"][0]["parts"],
+            json!([1, 2, 3])
+        );
+        assert_eq!(response["result"]["regions"]["2"]["defaultName"], "England");
+    }
+
+    #[test]
+    fn find_names_in_requested_languages() {
+        let response = find(r#"{ "connection": 0, "query": "londres", "languages": ["fr"] }"#);
+
+        assert_eq!(region_ids(&response), vec![3]);
+        assert_eq!(response["result"]["regions"]["3"]["names"]["fr"], "Londres");
+    }
+
+    #[test]
+    fn find_regions_with_all_names() {
+        let names = json!({ "en": "London", "fr": "Londres" });
+
+        for query in &["london", "londres"] {
+            let request = format!(r#"{{ "connection": 0, "query": "kingdom >> {}" }}"#, query);
+            let response = find(&request);
+            let region = &response["result"]["regions"]["3"];
+
+            assert_eq!(region_ids(&response), vec![3]);
+            assert_eq!(region["defaultName"], "London");
+            assert_eq!(region["names"], names);
         }
     }
+
+    #[test]
+    fn find_hierarchies_with_filter() {
+        let response = find(
+            r#"{ "connection": 0, "query": "o", "filter": { "types": ["city"], "maxDepth": 3 } }"#,
+        );
+
+        assert_eq!(region_ids(&response), vec![3, 9]);
+    }
+
+    #[test]
+    fn find_hierarchies_by_regex() {
+        let response =
+            find(r#"{ "connection": 0, "query": "[es].+land$ > n", "matchMode": "regex" }"#);
+
+        assert_eq!(region_ids(&response), vec![3, 6]);
+    }
+
+    #[test]
+    fn page_hierarchies_by_connection_limit() {
+        let response = find(r#"{ "connection": 0, "query": "n" }"#);
+
+        assert_eq!(response["result"]["total"], 9);
+        assert_eq!(region_ids(&response), vec![1, 2, 3]);
+
+        let response = find(r#"{ "connection": 0, "query": "n", "offset": 7, "limit": 5 }"#);
+
+        assert_eq!(region_ids(&response), vec![8, 9]);
+    }
+
+    #[test]
+    fn find_strict_hierarchies_without_unloaded_parts() {
+        let response = find(r#"{ "connection": 0, "query": "canada > halifax" }"#);
+
+        assert_eq!(region_ids(&response), vec![10]);
+        assert!(response["result"].get("missingParts").is_none());
+
+        let response = find(r#"{ "connection": 0, "query": "canada > halifax", "strict": true }"#);
+
+        assert!(region_ids(&response).is_empty());
+        assert_eq!(response["result"]["missingParts"], json!([99]));
+
+        let response = find(r#"{ "connection": 0, "query": "canada >> halifax", "strict": true }"#);
+
+        assert_eq!(region_ids(&response), vec![10]);
+    }
+
+    #[test]
+    fn explain_found_and_rejected_hierarchies() {
+        let response = find(
+            r#"{ "connection": 0, "query": "royaume >> londres", "languages": ["fr"], "explain": true }"#,
+        );
+
+        assert_eq!(region_ids(&response), vec![3]);
+        assert_eq!(
+            response["result"]["hierarchies"][0]["matches"],
+            json!([
+                { "part": 0, "regionId": 1, "name": "Royaume-Uni", "language": "fr" },
+                { "part": 1, "regionId": 3, "name": "Londres", "language": "fr" },
+            ])
+        );
+        assert_eq!(response["result"]["rejected"], json!([]));
+
+        let response = find(r#"{ "connection": 0, "query": "england > london", "explain": true }"#);
+
+        assert_eq!(region_ids(&response), vec![3]);
+        assert_eq!(
+            response["result"]["rejected"],
+            json!([{
+                "id": 9,
+                "regionId": 9,
+                "reason": "Query part 0 does not match any parent",
+                "part": 0,
+            }])
+        );
+    }
+
+    #[test]
+    fn page_rejected_hierarchies() {
+        let rejected_ids = |request: &str| -> Vec<i64> {
+            find(request)["result"]["rejected"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|hierarchy| hierarchy["id"].as_i64().unwrap())
+                .collect()
+        };
+
+        // Page size is limited by connection `max_results`.
+        assert_eq!(
+            rejected_ids(r#"{ "connection": 0, "query": "scotland > o", "explain": true }"#),
+            vec![1, 3, 5]
+        );
+        assert_eq!(
+            rejected_ids(
+                r#"{ "connection": 0, "query": "scotland > o", "explain": true, "offset": 2, "limit": 2 }"#
+            ),
+            vec![5, 8]
+        );
+    }
+
+    #[test]
+    fn group_hierarchies_by_region() {
+        let response = find(r#"{ "connection": 0, "query": "derry" }"#);
+
+        assert_eq!(response["result"]["total"], 4);
+
+        let response = find(r#"{ "connection": 0, "query": "derry", "group": true }"#);
+        let hierarchy = &response["result"]["hierarchies"][0];
+        let alternates: Vec<_> = hierarchy["alternates"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|alternate| (alternate["id"].clone(), alternate["parts"].clone()))
+            .collect();
+
+        assert_eq!(response["result"]["total"], 1);
+        assert_eq!(hierarchy["id"], 11);
+        assert_eq!(hierarchy["parts"], json!([1, 11]));
+        assert!(hierarchy.get("collapsed").is_none());
+        assert_eq!(
+            alternates,
+            vec![
+                (json!(11), json!([1, 11])),
+                (json!(11), json!([1, 2, 11])),
+                (json!(12), json!([1, 11])),
+            ]
+        );
+
+        let response =
+            find(r#"{ "connection": 0, "query": "derry", "group": true, "collapse": true }"#);
+        let hierarchy = &response["result"]["hierarchies"][0];
+
+        assert_eq!(hierarchy["collapsed"], 2);
+        assert_eq!(hierarchy["alternates"].as_array().unwrap().len(), 1);
+        assert_eq!(hierarchy["alternates"][0]["parts"], json!([1, 2, 11]));
+    }
+
+    #[test]
+    fn reject_collapse_without_grouping() {
+        let response = find(r#"{ "connection": 0, "query": "derry", "collapse": true }"#);
+
+        assert_eq!(response["success"], false);
+    }
+
+    #[test]
+    fn find_on_connection_list() {
+        let response = find(r#"{ "connection": [0, 5], "query": "camden" }"#);
+        let connections = response["result"]["connections"].as_array().unwrap();
+
+        assert_eq!(connections.len(), 2);
+        assert_eq!(connections[0]["success"], true);
+        assert_eq!(connections[0]["description"], "Fixtures");
+        assert_eq!(connections[0]["result"]["hierarchies"][0]["regionId"], 4);
+        assert_eq!(connections[1]["success"], false);
+    }
+
+    #[test]
+    fn report_query_syntax_error_position() {
+        let response = find(r#"{ "connection": 0, "query": "england >> " }"#);
+
+        assert_eq!(response["success"], false);
+        assert_eq!(response["position"], 12);
+    }
 }
@@ -0,0 +1,93 @@
+use crate::cache::RegionCacheRef;
+use crate::config::ConfigRef;
+use crate::database::ClientPoolRef;
+use crate::handler::common;
+use crate::handler::common::Response;
+use crate::handler::error::HandlerError;
+use crate::handler::error::HandlerResult;
+use crate::handler::util::handle_request;
+use crate::manager::DynamicConnectionsRef;
+use iron::middleware::Handler;
+use iron::IronResult;
+use iron::Request as IromRequest;
+use iron::Response as IromResponse;
+
+#[derive(Debug)]
+pub struct FindRegionByPointHandler {
+    config: ConfigRef,
+    dynamic_connections: DynamicConnectionsRef,
+    region_cache: RegionCacheRef,
+    client_pool: ClientPoolRef,
+}
+
+impl FindRegionByPointHandler {
+    pub fn new(
+        config: ConfigRef,
+        dynamic_connections: DynamicConnectionsRef,
+        region_cache: RegionCacheRef,
+        client_pool: ClientPoolRef,
+    ) -> FindRegionByPointHandler {
+        FindRegionByPointHandler {
+            config,
+            dynamic_connections,
+            region_cache,
+            client_pool,
+        }
+    }
+
+    fn validate_point(&self, latitude: f64, longitude: f64) -> HandlerResult<()> {
+        if !(-90.0..=90.0).contains(&latitude) {
+            Err(HandlerError::new(&format!(
+                "Latitude must be in range [-90, 90], but {} given",
+                latitude
+            )))
+        } else if !(-180.0..=180.0).contains(&longitude) {
+            Err(HandlerError::new(&format!(
+                "Longitude must be in range [-180, 180], but {} given",
+                longitude
+            )))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Handler for FindRegionByPointHandler {
+    fn handle(&self, request: &mut IromRequest) -> IronResult<IromResponse> {
+        handle_request(request, move |request: Request| {
+            self.validate_point(request.latitude, request.longitude)?;
+
+            let mut client = common::prepare_connection(
+                &self.config,
+                &self.dynamic_connections,
+                &self.region_cache,
+                &self.client_pool,
+                request.connection,
+            )?;
+
+            client.set_languages(&request.languages);
+
+            let point_regions = client
+                .regions_by_point(request.latitude, request.longitude)
+                .map_err(|error| {
+                    HandlerError::database_error("Failed to query region by point", error)
+                })?;
+            let hierarchies =
+                common::collect_hierarchy(&mut client, point_regions.keys().cloned())?;
+            let all_regions =
+                common::collect_all_regions(&mut client, &point_regions, &hierarchies)?;
+
+            Ok(Response::new(all_regions, &hierarchies))
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Request {
+    connection: usize,
+    latitude: f64,
+    longitude: f64,
+    #[serde(default)]
+    languages: Vec<String>,
+}
@@ -0,0 +1,325 @@
+use crate::cache::RegionCacheRef;
+use crate::config::ConfigRef;
+use crate::database::ClientPoolRef;
+use crate::database::DatabaseClient;
+use crate::database::Hierarchy as DbHierarchy;
+use crate::database::Region as DbRegion;
+use crate::handler::common;
+use crate::handler::error::HandlerError;
+use crate::handler::error::HandlerResult;
+use crate::handler::util::handle_request;
+use crate::manager::DynamicConnectionsRef;
+use iron::middleware::Handler;
+use iron::IronResult;
+use iron::Request as IromRequest;
+use iron::Response as IromResponse;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+#[derive(Debug)]
+pub struct LintHandler {
+    config: ConfigRef,
+    dynamic_connections: DynamicConnectionsRef,
+    region_cache: RegionCacheRef,
+    client_pool: ClientPoolRef,
+}
+
+const MAX_EXAMPLES: usize = 10;
+const REGIONS_CHUNK_SIZE: usize = 10_000;
+
+impl LintHandler {
+    pub fn new(
+        config: ConfigRef,
+        dynamic_connections: DynamicConnectionsRef,
+        region_cache: RegionCacheRef,
+        client_pool: ClientPoolRef,
+    ) -> LintHandler {
+        LintHandler {
+            config,
+            dynamic_connections,
+            region_cache,
+            client_pool,
+        }
+    }
+
+    fn collect_regions(
+        &self,
+        client: &mut DatabaseClient,
+        hierarchies: &[DbHierarchy],
+    ) -> HandlerResult<HashMap<i64, DbRegion>> {
+        let region_ids: Vec<_> = hierarchies
+            .iter()
+            .flat_map(hierarchy_regions)
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        let mut result = HashMap::new();
+
+        for chunk in region_ids.chunks(REGIONS_CHUNK_SIZE) {
+            let regions = client.uncached_regions_by_id(chunk).map_err(|error| {
+                HandlerError::database_error("Failed to query region name", error)
+            })?;
+
+            result.extend(regions);
+        }
+
+        Ok(result)
+    }
+
+    fn lint(regions: &HashMap<i64, DbRegion>, hierarchies: &[DbHierarchy]) -> Response {
+        let mut level_gaps = Anomaly::new("levelGap");
+        let mut duplicate_parts = Anomaly::new("duplicatePart");
+        let mut missing_names = Anomaly::new("missingNames");
+        let mut missing_default_names = Anomaly::new("missingDefaultName");
+        let mut missing_name_ids = BTreeSet::new();
+
+        for hierarchy in hierarchies {
+            let parts = hierarchy.parts();
+
+            if hierarchy.has_gap() {
+                level_gaps.insert(hierarchy.id());
+            }
+
+            if parts.iter().collect::<HashSet<_>>().len() != parts.len() {
+                duplicate_parts.insert(hierarchy.id());
+            }
+
+            for id in hierarchy_regions(hierarchy) {
+                if !regions.contains_key(&id) {
+                    missing_name_ids.insert(id);
+                }
+            }
+        }
+
+        for id in missing_name_ids {
+            missing_names.insert(id);
+        }
+
+        let mut region_ids: Vec<_> = regions.keys().collect();
+
+        region_ids.sort();
+
+        for id in region_ids {
+            if !regions[id].has_default_name() {
+                missing_default_names.insert(*id);
+            }
+        }
+
+        let mut cycles = Anomaly::new("cycle");
+
+        for id in find_cycles(hierarchies) {
+            cycles.insert(id);
+        }
+
+        Response {
+            hierarchies: hierarchies.len(),
+            regions: regions.len(),
+            anomalies: vec![
+                cycles,
+                duplicate_parts,
+                level_gaps,
+                missing_names,
+                missing_default_names,
+            ],
+        }
+    }
+}
+
+impl Handler for LintHandler {
+    fn handle(&self, request: &mut IromRequest) -> IronResult<IromResponse> {
+        handle_request(request, move |request: Request| {
+            let mut client = common::prepare_connection(
+                &self.config,
+                &self.dynamic_connections,
+                &self.region_cache,
+                &self.client_pool,
+                request.connection,
+            )?;
+            let hierarchies = client.all_hierarchies().map_err(|error| {
+                HandlerError::database_error("Failed to query all hierarchies", error)
+            })?;
+            let regions = self.collect_regions(&mut client, &hierarchies)?;
+
+            Ok(LintHandler::lint(&regions, &hierarchies))
+        })
+    }
+}
+
+/// Returns all regions referenced by hierarchy: its parts, hierarchy region and found region.
+fn hierarchy_regions(hierarchy: &DbHierarchy) -> impl Iterator<Item = i64> + '_ {
+    hierarchy
+        .parts()
+        .iter()
+        .cloned()
+        .chain(vec![hierarchy.id(), hierarchy.region_id()])
+}
+
+/// Finds regions which are ancestors of itself. Every pair of neighbor hierarchy parts defines
+/// edge from parent to child, regions which are parents of itself and regions of every strongly
+/// connected component with more than one region are returned in sorted order.
+fn find_cycles(hierarchies: &[DbHierarchy]) -> BTreeSet<i64> {
+    let mut children: HashMap<i64, BTreeSet<i64>> = HashMap::new();
+    let mut parents: HashMap<i64, BTreeSet<i64>> = HashMap::new();
+    let mut result = BTreeSet::new();
+
+    for hierarchy in hierarchies {
+        for window in hierarchy.parts().windows(2) {
+            if window[0] == window[1] {
+                result.insert(window[0]);
+            } else {
+                children.entry(window[0]).or_default().insert(window[1]);
+                parents.entry(window[1]).or_default().insert(window[0]);
+            }
+        }
+    }
+
+    let mut nodes: Vec<_> = children.keys().chain(parents.keys()).cloned().collect();
+
+    nodes.sort_unstable();
+    nodes.dedup();
+
+    // Kosaraju algorithm: regions ordered by search finish time in parent to child graph are
+    // searched in reverse order in child to parent graph, every search finds single component.
+    let mut visited = HashSet::new();
+    let mut order = Vec::with_capacity(nodes.len());
+
+    for &node in &nodes {
+        depth_first_search(&children, node, &mut visited, &mut order);
+    }
+
+    let mut visited = HashSet::new();
+
+    for &node in order.iter().rev() {
+        let mut component = Vec::new();
+
+        depth_first_search(&parents, node, &mut visited, &mut component);
+
+        if component.len() > 1 {
+            result.extend(component);
+        }
+    }
+
+    result
+}
+
+/// Appends all not visited regions reachable from `start` to `finished` in order of search
+/// finish time.
+fn depth_first_search(
+    edges: &HashMap<i64, BTreeSet<i64>>,
+    start: i64,
+    visited: &mut HashSet<i64>,
+    finished: &mut Vec<i64>,
+) {
+    if !visited.insert(start) {
+        return;
+    }
+
+    let mut stack = vec![(start, neighbors(edges, start))];
+
+    while let Some((node, next)) = stack.last_mut() {
+        match next.pop() {
+            Some(next) if visited.insert(next) => {
+                let next_neighbors = neighbors(edges, next);
+
+                stack.push((next, next_neighbors));
+            }
+            Some(_) => {}
+            None => {
+                finished.push(*node);
+                stack.pop();
+            }
+        }
+    }
+}
+
+fn neighbors(edges: &HashMap<i64, BTreeSet<i64>>, id: i64) -> Vec<i64> {
+    edges
+        .get(&id)
+        .map(|ids| ids.iter().rev().cloned().collect())
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Request {
+    connection: usize,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Response {
+    hierarchies: usize,
+    regions: usize,
+    anomalies: Vec<Anomaly>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Anomaly {
+    kind: &'static str,
+    count: usize,
+    examples: Vec<i64>,
+}
+
+impl Anomaly {
+    fn new(kind: &'static str) -> Anomaly {
+        Anomaly {
+            kind,
+            count: 0,
+            examples: Vec::new(),
+        }
+    }
+
+    fn insert(&mut self, id: i64) {
+        if self.examples.len() < MAX_EXAMPLES {
+            self.examples.push(id);
+        }
+
+        self.count += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cycles(paths: &[&[i64]]) -> Vec<i64> {
+        let hierarchies: Vec<_> = paths
+            .iter()
+            .enumerate()
+            .map(|(id, path)| {
+                let levels: Vec<_> = path.iter().cloned().map(Some).collect();
+
+                DbHierarchy::new(id as i64 + 100, id as i64 + 100, &levels)
+            })
+            .collect();
+
+        find_cycles(&hierarchies).into_iter().collect()
+    }
+
+    #[test]
+    fn find_self_loop() {
+        assert_eq!(cycles(&[&[1, 2, 2, 3]]), vec![2]);
+    }
+
+    #[test]
+    fn find_two_region_cycle() {
+        assert_eq!(cycles(&[&[1, 2, 3], &[3, 2, 4], &[5, 6]]), vec![2, 3]);
+    }
+
+    #[test]
+    fn find_no_cycles_in_chain() {
+        assert!(cycles(&[&[1, 2, 3, 4], &[1, 2, 5], &[2, 3]]).is_empty());
+    }
+
+    #[test]
+    fn report_hierarchy_regions_without_names() {
+        let hierarchies = vec![DbHierarchy::new(7, 8, &[Some(1), Some(2)])];
+        let response = LintHandler::lint(&HashMap::new(), &hierarchies);
+        let missing_names = &response.anomalies[3];
+
+        assert_eq!(missing_names.kind, "missingNames");
+        assert_eq!(missing_names.examples, vec![1, 2, 7, 8]);
+    }
+}
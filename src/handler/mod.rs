@@ -1,7 +1,23 @@
+mod cache;
+mod children;
+mod common;
 mod connections;
+mod diff;
 mod error;
 mod findregion;
+mod findregionbypoint;
+mod lint;
+mod query;
+mod region;
+mod suggest;
 mod util;
 
+pub use cache::CacheHandler;
+pub use children::ChildrenHandler;
 pub use connections::ConnectionsHandler;
+pub use diff::DiffHandler;
 pub use findregion::FindRegionHandler;
+pub use findregionbypoint::FindRegionByPointHandler;
+pub use lint::LintHandler;
+pub use region::RegionHandler;
+pub use suggest::SuggestHandler;
@@ -0,0 +1,1111 @@
+use crate::database::Hierarchy as DbHierarchy;
+use crate::database::Region as DbRegion;
+use crate::database::RegionName;
+use crate::handler::error::HandlerError;
+use crate::handler::error::HandlerResult;
+use regex::Regex;
+use regex::RegexBuilder;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::fmt;
+use std::iter::Enumerate;
+use std::iter::Peekable;
+use std::str::Chars;
+
+const ANCHOR: char = '^';
+const SEPARATOR: char = '>';
+const ALTERNATIVE: char = '|';
+const EXCLUSION: char = '!';
+const QUOTE: char = '"';
+
+/// Parsed region query. Query consists of parts separated by `>` (direct parent) or `>>` (any
+/// ancestor), the last part describes required region and all previous parts describe its
+/// parents. Every part contains one or more names separated by `|`, quoted names must match
+/// exactly, other names are matched using requested `MatchMode`, names started with `!` exclude
+/// regions. Query started with `^` requires the first part to match top level region, except in
+/// regex mode where `^` is part of the pattern. Strict query does not match parents without loaded
+/// names.
+#[derive(Debug)]
+pub struct Query {
+    anchored: bool,
+    strict: bool,
+    explain: bool,
+    parts: Vec<QueryPart>,
+    filter: LeafFilter,
+}
+
+impl Query {
+    pub fn filter(&self) -> &LeafFilter {
+        &self.filter
+    }
+
+    pub fn set_filter(&mut self, filter: LeafFilter) -> HandlerResult<()> {
+        match (filter.min_depth, filter.max_depth) {
+            (Some(0), _) | (_, Some(0)) => {
+                Err(HandlerError::new("Depth must be greater than zero"))
+            }
+            (Some(min_depth), Some(max_depth)) if min_depth > max_depth => Err(HandlerError::new(
+                "Minimal depth must not be greater than maximal depth",
+            )),
+            _ => {
+                self.filter = filter;
+
+                Ok(())
+            }
+        }
+    }
+
+    pub fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    pub fn is_explain(&self) -> bool {
+        self.explain
+    }
+
+    pub fn set_explain(&mut self, explain: bool) {
+        self.explain = explain;
+    }
+
+    pub fn leaf(&self) -> &QueryPart {
+        &self.parts[self.parts.len() - 1]
+    }
+
+    pub fn parents(&self) -> &[QueryPart] {
+        &self.parts[..self.parts.len() - 1]
+    }
+
+    /// Returns query without leaf, the last parent becomes leaf of returned query.
+    pub fn into_parent_query(mut self) -> Option<Query> {
+        if self.parts.len() > 1 {
+            self.parts.pop();
+
+            Some(self)
+        } else {
+            None
+        }
+    }
+}
+
+/// Restricts required regions by depth (one based position of region in hierarchy) and by region
+/// type. Types can be used only if query schema defines type attribute.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LeafFilter {
+    min_depth: Option<usize>,
+    max_depth: Option<usize>,
+    #[serde(default)]
+    types: Vec<String>,
+}
+
+impl LeafFilter {
+    pub fn has_types(&self) -> bool {
+        !self.types.is_empty()
+    }
+
+    fn check(&self, depth: usize, region: Option<&DbRegion>) -> Result<(), Rejection> {
+        let type_matches = !self.has_types()
+            || region
+                .and_then(DbRegion::region_type)
+                .is_some_and(|region_type| self.types.iter().any(|name| name == region_type));
+
+        if self.min_depth.is_some_and(|min_depth| depth < min_depth)
+            || self.max_depth.is_some_and(|max_depth| depth > max_depth)
+        {
+            Err(Rejection::Depth(depth))
+        } else if !type_matches {
+            Err(Rejection::RegionType)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Defines how query names are compared with region names. Quoted names are always matched
+/// exactly.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchMode {
+    #[default]
+    Substring,
+    Prefix,
+    Exact,
+    Glob,
+    Regex,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Relation {
+    Parent,
+    Ancestor,
+}
+
+#[derive(Debug)]
+pub struct QueryPart {
+    relation: Relation,
+    names: Vec<QueryName>,
+}
+
+impl QueryPart {
+    pub fn names(&self) -> impl Iterator<Item = &QueryName> {
+        self.names.iter().filter(|name| !name.excluded)
+    }
+
+    /// Checks that part contains no names at all, only leaf of suggest query may be empty.
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn is_excluded(&self, region: &DbRegion) -> bool {
+        self.names
+            .iter()
+            .filter(|name| name.excluded)
+            .any(|name| name.matches(region))
+    }
+
+    pub fn matches(&self, region: &DbRegion) -> bool {
+        if self.is_excluded(region) {
+            return false;
+        }
+
+        let mut names = self.names().peekable();
+
+        names.peek().is_none() || names.any(|name| name.matches(region))
+    }
+
+    /// Returns region name matched to not excluded names of this part.
+    pub fn matched_name<'a>(&self, region: &'a DbRegion) -> Option<&'a RegionName> {
+        region.find_name(|name| self.names().any(|query_name| query_name.matches_name(name)))
+    }
+}
+
+#[derive(Debug)]
+pub struct QueryName {
+    text: String,
+    mode: MatchMode,
+    regex: Option<Regex>,
+    excluded: bool,
+}
+
+impl QueryName {
+    fn new(
+        text: &str,
+        mode: MatchMode,
+        excluded: bool,
+        position: usize,
+    ) -> HandlerResult<QueryName> {
+        let (text, regex) = match mode {
+            MatchMode::Regex => (text.to_string(), Some(compile_regex(text, position)?)),
+            MatchMode::Glob => {
+                let text = text.to_lowercase();
+                let regex = compile_regex(&glob_to_regex(&text), position)?;
+
+                (text, Some(regex))
+            }
+            _ => (text.to_lowercase(), None),
+        };
+
+        Ok(QueryName {
+            text,
+            mode,
+            regex,
+            excluded,
+        })
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn mode(&self) -> MatchMode {
+        self.mode
+    }
+
+    /// Returns SQL `like` pattern for `regions_by_name` query. Regular expressions can not be
+    /// represented as `like` pattern and should be searched using `regions_by_regex` query.
+    pub fn sql_pattern(&self) -> String {
+        match self.mode {
+            MatchMode::Substring => format!("%{}%", escape_like(&self.text)),
+            MatchMode::Prefix => format!("{}%", escape_like(&self.text)),
+            MatchMode::Exact => escape_like(&self.text),
+            MatchMode::Glob => glob_to_like(&self.text),
+            MatchMode::Regex => self.text.clone(),
+        }
+    }
+
+    pub fn matches(&self, region: &DbRegion) -> bool {
+        region.matches_name(|name| self.matches_name(name))
+    }
+
+    /// Checks that lower case region name matches this name.
+    pub fn matches_name(&self, name: &str) -> bool {
+        match (self.mode, &self.regex) {
+            (MatchMode::Substring, _) => name.contains(&self.text),
+            (MatchMode::Prefix, _) => name.starts_with(&self.text),
+            (MatchMode::Exact, _) => name == self.text,
+            (_, Some(regex)) => regex.is_match(name),
+            (_, None) => false,
+        }
+    }
+}
+
+fn compile_regex(pattern: &str, position: usize) -> HandlerResult<Regex> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(true)
+        .build()
+        .map_err(|_| HandlerError::syntax_error("Invalid regular expression", position))
+}
+
+/// Escapes wildcard and escape characters of SQL `like` pattern.
+pub fn escape_like(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+
+    for ch in text.chars() {
+        if ch == '\\' || ch == '%' || ch == '_' {
+            result.push('\\');
+        }
+
+        result.push(ch);
+    }
+
+    result
+}
+
+fn glob_to_like(glob: &str) -> String {
+    let mut result = String::with_capacity(glob.len());
+
+    for ch in glob.chars() {
+        match ch {
+            '*' => result.push('%'),
+            '?' => result.push('_'),
+            '\\' | '%' | '_' => {
+                result.push('\\');
+                result.push(ch);
+            }
+            _ => result.push(ch),
+        }
+    }
+
+    result
+}
+
+fn glob_to_regex(glob: &str) -> String {
+    let mut result = String::with_capacity(glob.len() + 2);
+
+    result.push('^');
+
+    for ch in glob.chars() {
+        match ch {
+            '*' => result.push_str(".*"),
+            '?' => result.push('.'),
+            _ => result.push_str(&regex::escape(&ch.to_string())),
+        }
+    }
+
+    result.push('$');
+    result
+}
+
+/// Name of query part which match mode is not known until the whole part is parsed.
+struct ParsedName {
+    text: String,
+    exact: bool,
+    excluded: bool,
+    position: usize,
+}
+
+struct QueryParser<'a> {
+    chars: Peekable<Enumerate<Chars<'a>>>,
+    length: usize,
+    mode: MatchMode,
+    leaf_mode: MatchMode,
+    empty_leaf: bool,
+}
+
+impl<'a> QueryParser<'a> {
+    fn new(
+        query: &str,
+        mode: MatchMode,
+        leaf_mode: MatchMode,
+        empty_leaf: bool,
+    ) -> QueryParser<'_> {
+        QueryParser {
+            chars: query.chars().enumerate().peekable(),
+            length: query.chars().count(),
+            mode,
+            leaf_mode,
+            empty_leaf,
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(&(_, ch)) = self.chars.peek() {
+            if !ch.is_whitespace() {
+                break;
+            }
+
+            self.chars.next();
+        }
+    }
+
+    fn position(&mut self) -> usize {
+        self.chars.peek().map_or(self.length, |&(index, _)| index)
+    }
+
+    fn next_if(&mut self, expected: char) -> bool {
+        match self.chars.peek() {
+            Some(&(_, ch)) if ch == expected => {
+                self.chars.next();
+
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn parse(mut self) -> HandlerResult<Query> {
+        self.skip_whitespace();
+
+        // Leading `^` of regular expression anchors the pattern, not the query.
+        let anchored = self.mode != MatchMode::Regex && self.next_if(ANCHOR);
+        let mut parts = Vec::new();
+        let mut relation = Relation::Ancestor;
+
+        loop {
+            self.skip_whitespace();
+
+            // Partially typed query may end with separator, its leaf has no names yet.
+            if self.empty_leaf && !parts.is_empty() && self.chars.peek().is_none() {
+                parts.push(QueryPart {
+                    relation,
+                    names: Vec::new(),
+                });
+
+                break;
+            }
+
+            parts.push(self.parse_part(relation)?);
+
+            match self.chars.next() {
+                Some((_, SEPARATOR)) if self.next_if(SEPARATOR) => relation = Relation::Ancestor,
+                Some((_, SEPARATOR)) => relation = Relation::Parent,
+                Some((index, ch)) => {
+                    return Err(HandlerError::syntax_error(
+                        &format!("Unexpected character `{}`", ch),
+                        index + 1,
+                    ))
+                }
+                None => break,
+            }
+        }
+
+        Ok(Query {
+            anchored,
+            strict: false,
+            explain: false,
+            parts,
+            filter: LeafFilter::default(),
+        })
+    }
+
+    fn parse_part(&mut self, relation: Relation) -> HandlerResult<QueryPart> {
+        let mut parsed = vec![self.parse_name()?];
+
+        while self.next_if(ALTERNATIVE) {
+            parsed.push(self.parse_name()?);
+        }
+
+        // The last part is the leaf, it is the only part not followed by separator.
+        let mode = match self.chars.peek() {
+            Some(_) => self.mode,
+            None => self.leaf_mode,
+        };
+        let names = parsed
+            .into_iter()
+            .map(|name| {
+                let mode = if name.exact { MatchMode::Exact } else { mode };
+
+                QueryName::new(&name.text, mode, name.excluded, name.position)
+            })
+            .collect::<HandlerResult<_>>()?;
+
+        Ok(QueryPart { relation, names })
+    }
+
+    fn parse_name(&mut self) -> HandlerResult<ParsedName> {
+        self.skip_whitespace();
+
+        let excluded = self.next_if(EXCLUSION);
+
+        self.skip_whitespace();
+
+        let start = self.position();
+        let exact = self.next_if(QUOTE);
+        let text = if exact {
+            self.parse_quoted(start)?
+        } else {
+            self.parse_bare()
+        };
+
+        self.skip_whitespace();
+
+        if text.is_empty() {
+            Err(HandlerError::syntax_error(
+                "Region name must not be empty",
+                start + 1,
+            ))
+        } else {
+            Ok(ParsedName {
+                text,
+                exact,
+                excluded,
+                position: start + 1,
+            })
+        }
+    }
+
+    fn parse_quoted(&mut self, start: usize) -> HandlerResult<String> {
+        let mut text = String::new();
+
+        loop {
+            match self.chars.next() {
+                Some((_, QUOTE)) => break Ok(text),
+                Some((_, ch)) => text.push(ch),
+                None => {
+                    break Err(HandlerError::syntax_error(
+                        "Unterminated quoted name",
+                        start + 1,
+                    ))
+                }
+            }
+        }
+    }
+
+    fn parse_bare(&mut self) -> String {
+        let mut text = String::new();
+
+        while let Some(&(_, ch)) = self.chars.peek() {
+            if ch == SEPARATOR || ch == ALTERNATIVE || ch == QUOTE {
+                break;
+            }
+
+            text.push(ch);
+            self.chars.next();
+        }
+
+        text.trim_end().into()
+    }
+}
+
+pub fn prepare_query(query: &str, mode: MatchMode) -> HandlerResult<Query> {
+    parse_query(query, mode, mode, false)
+}
+
+/// Prepares query of partially typed region name. Leaf names are prefixes matched regardless of
+/// requested mode, parents are matched using requested mode. Query ended with separator has empty
+/// leaf.
+pub fn prepare_suggest_query(query: &str, mode: MatchMode) -> HandlerResult<Query> {
+    parse_query(query, mode, MatchMode::Prefix, true)
+}
+
+fn parse_query(
+    query: &str,
+    mode: MatchMode,
+    leaf_mode: MatchMode,
+    empty_leaf: bool,
+) -> HandlerResult<Query> {
+    if query.trim().is_empty() {
+        return Err(HandlerError::new("Region name query must not be empty"));
+    }
+
+    let query = QueryParser::new(query, mode, leaf_mode, empty_leaf).parse()?;
+    let names: Vec<_> = query.leaf().names().collect();
+
+    if query.leaf().is_empty() {
+        Ok(query)
+    } else if names.is_empty() {
+        Err(HandlerError::new(
+            "Region name must contain at least one not excluded name",
+        ))
+    } else if !names
+        .iter()
+        .all(|name| name.text().chars().any(|ch| ch.is_alphanumeric()))
+    {
+        Err(HandlerError::new(
+            "Region name must contains at least one letter or digit",
+        ))
+    } else {
+        Ok(query)
+    }
+}
+
+pub fn collect_query_hierarchies<'a>(
+    query: &Query,
+    regions: &HashMap<i64, DbRegion>,
+    hierarchies: &'a [DbHierarchy],
+) -> Vec<&'a DbHierarchy> {
+    hierarchies
+        .iter()
+        .filter(|hierarchy| is_hierarchy_matches(hierarchy, query, regions))
+        .collect()
+}
+
+/// Returns sorted identifiers of hierarchy parts without loaded names.
+pub fn missing_parts(hierarchies: &[DbHierarchy], regions: &HashMap<i64, DbRegion>) -> Vec<i64> {
+    let parts: BTreeSet<_> = hierarchies
+        .iter()
+        .flat_map(|hierarchy| {
+            hierarchy
+                .parts()
+                .iter()
+                .cloned()
+                .chain(Some(hierarchy.id()))
+        })
+        .filter(|part| !regions.contains_key(part))
+        .collect();
+
+    parts.into_iter().collect()
+}
+
+pub fn is_hierarchy_matches(
+    hierarchy: &DbHierarchy,
+    query: &Query,
+    regions: &HashMap<i64, DbRegion>,
+) -> bool {
+    match_hierarchy(hierarchy, query, regions).is_ok()
+}
+
+/// Explains matching of hierarchy to query. Returns matched region for every matched query part
+/// ordered from the top level region to required region, or the reason of hierarchy rejection.
+pub fn explain_hierarchy(
+    hierarchy: &DbHierarchy,
+    query: &Query,
+    regions: &HashMap<i64, DbRegion>,
+) -> Result<Vec<PartMatch>, Rejection> {
+    let hierarchy_parts = hierarchy.parts();
+    let leaf = query.parts.len() - 1;
+    let mut matches: Vec<_> = match_hierarchy(hierarchy, query, regions)?
+        .into_iter()
+        .map(|(part, index)| {
+            PartMatch::new(&query.parts[part], part, hierarchy_parts[index], regions)
+        })
+        .collect();
+
+    matches.push(PartMatch::new(query.leaf(), leaf, hierarchy.id(), regions));
+
+    Ok(matches)
+}
+
+/// Returns pairs of query part index and matched hierarchy part index ordered from the top level
+/// region. Query parts accepted without checking are not included.
+fn match_hierarchy(
+    hierarchy: &DbHierarchy,
+    query: &Query,
+    regions: &HashMap<i64, DbRegion>,
+) -> Result<Vec<(usize, usize)>, Rejection> {
+    let hierarchy_parts = hierarchy.parts();
+    // Parents are located before required region, if hierarchy does not contain required region
+    // all parts treated as parents.
+    let leaf_index = hierarchy_parts
+        .iter()
+        .rposition(|&part| part == hierarchy.id() || part == hierarchy.region_id())
+        .unwrap_or(hierarchy_parts.len());
+
+    query
+        .filter
+        .check(leaf_index + 1, regions.get(&hierarchy.id()))?;
+
+    // Anchored query without parents requires required region to be top level region.
+    if query.anchored && query.parents().is_empty() && leaf_index != 0 {
+        return Err(Rejection::Anchor);
+    }
+
+    let n_parts = query.parents().len();
+    let mut matcher = ParentsMatcher {
+        query,
+        regions,
+        matched: Vec::new(),
+        failed_part: n_parts,
+    };
+
+    if matcher.matches(n_parts, &hierarchy_parts[..leaf_index]) {
+        matcher.matched.reverse();
+
+        Ok(matcher.matched)
+    } else {
+        Err(Rejection::Part(matcher.failed_part))
+    }
+}
+
+/// Matches query parents to hierarchy parts. Keeps the current chain of matched parts and the
+/// upper query part which never matched to explain matching result.
+struct ParentsMatcher<'a> {
+    query: &'a Query,
+    regions: &'a HashMap<i64, DbRegion>,
+    matched: Vec<(usize, usize)>,
+    failed_part: usize,
+}
+
+impl ParentsMatcher<'_> {
+    /// Checks that first `n_parts` query parts match to hierarchy parts. Query parts are matched
+    /// from the nearest parent to the top level region, so the last query part must be placed
+    /// directly before the end of `hierarchy_parts` if its child relation is `Relation::Parent`
+    /// or anywhere before otherwise.
+    fn matches(&mut self, n_parts: usize, hierarchy_parts: &[i64]) -> bool {
+        if n_parts == 0 {
+            return true;
+        }
+
+        let query = self.query;
+        let query_part = &query.parts[n_parts - 1];
+        let start = match query.parts[n_parts].relation {
+            Relation::Parent => hierarchy_parts.len().saturating_sub(1),
+            Relation::Ancestor => 0,
+        };
+
+        self.failed_part = self.failed_part.min(n_parts - 1);
+
+        for index in (start..hierarchy_parts.len()).rev() {
+            // Anchored query requires the first part to be top level region.
+            if query.anchored && n_parts == 1 && index != 0 {
+                continue;
+            }
+
+            match self.regions.get(&hierarchy_parts[index]) {
+                // If region name is present in query - check remaining query parts with upper
+                // regions.
+                Some(region) if query_part.matches(region) => {
+                    self.matched.push((n_parts - 1, index));
+
+                    if self.matches(n_parts - 1, &hierarchy_parts[..index]) {
+                        return true;
+                    }
+
+                    self.matched.pop();
+                }
+                // If region name not in query - skip this region and continue search.
+                Some(_) => {}
+                // If this region not loaded - strict query skips it like not matched region.
+                None if query.strict => {}
+                // Otherwise accept this hierarchy to avoid false negative case.
+                None => {
+                    self.matched.push((n_parts - 1, index));
+
+                    return true;
+                }
+            }
+        }
+
+        false
+    }
+}
+
+/// Region matched to query part. Name is absent if region is not loaded or query part contains
+/// only excluded names.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PartMatch {
+    part: usize,
+    region_id: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    language: Option<String>,
+}
+
+impl PartMatch {
+    fn new(
+        query_part: &QueryPart,
+        part: usize,
+        region_id: i64,
+        regions: &HashMap<i64, DbRegion>,
+    ) -> PartMatch {
+        let name = regions
+            .get(&region_id)
+            .and_then(|region| query_part.matched_name(region));
+
+        PartMatch {
+            part,
+            region_id,
+            name: name.map(|name| name.name().into()),
+            language: name.map(|name| name.language().into()),
+        }
+    }
+}
+
+/// Reason of hierarchy rejection. Query parts are numbered from zero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rejection {
+    Depth(usize),
+    RegionType,
+    Anchor,
+    Part(usize),
+}
+
+impl Rejection {
+    pub fn part(&self) -> Option<usize> {
+        match *self {
+            Rejection::Part(part) => Some(part),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Rejection::Depth(depth) => write!(f, "Region depth {} is out of filter range", depth),
+            Rejection::RegionType => write!(f, "Region type does not match filter"),
+            Rejection::Anchor => write!(f, "Region is not top level region"),
+            Rejection::Part(part) => write!(f, "Query part {} does not match any parent", part),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::QuerySchemaSettings;
+    use crate::database::AttributeValue;
+    use crate::database::LanguageFilter;
+    use crate::database::RegionRecord;
+
+    /// Regions of hierarchy `Britain > England > London > Camden` with region types.
+    fn regions() -> HashMap<i64, DbRegion> {
+        let query_schema: QuerySchemaSettings =
+            serde_yaml::from_str("type_attribute: type").unwrap();
+        let records: Vec<_> = [
+            (1, "Britain", "country"),
+            (2, "England", "country"),
+            (3, "London", "city"),
+            (4, "Camden", "district"),
+        ]
+        .iter()
+        .map(|&(id, name, region_type)| {
+            let attributes = vec![("type".to_string(), AttributeValue::Text(region_type.into()))];
+
+            RegionRecord::new(id, "en", name, true, attributes)
+        })
+        .collect();
+
+        LanguageFilter::new(&[], &query_schema).build_regions(&records)
+    }
+
+    fn matches(query: &str, parts: &[Option<i64>]) -> bool {
+        matches_with(query, false, parts)
+    }
+
+    fn matches_with(query: &str, strict: bool, parts: &[Option<i64>]) -> bool {
+        let mut query = prepare_query(query, MatchMode::Substring).unwrap();
+
+        query.set_strict(strict);
+
+        let region_id = parts.iter().rev().find_map(|&part| part).unwrap();
+
+        is_hierarchy_matches(
+            &DbHierarchy::new(region_id, region_id, parts),
+            &query,
+            &regions(),
+        )
+    }
+
+    fn error_position(query: &str, mode: MatchMode) -> Option<usize> {
+        prepare_query(query, mode).unwrap_err().position()
+    }
+
+    #[test]
+    fn parse_parts_and_relations() {
+        let query = prepare_query(" ^britain >> england > london ", MatchMode::Substring).unwrap();
+        let relations: Vec<_> = query.parts.iter().map(|part| part.relation).collect();
+        let names: Vec<_> = query
+            .parts
+            .iter()
+            .flat_map(QueryPart::names)
+            .map(QueryName::text)
+            .collect();
+
+        assert!(query.anchored);
+        assert_eq!(
+            relations,
+            vec![Relation::Ancestor, Relation::Ancestor, Relation::Parent]
+        );
+        assert_eq!(names, vec!["britain", "england", "london"]);
+    }
+
+    #[test]
+    fn parse_alternatives_and_exclusions() {
+        let query =
+            prepare_query("uk | Britain >> London|!londonderry", MatchMode::Prefix).unwrap();
+        let leaf_names: Vec<_> = query.leaf().names().map(QueryName::text).collect();
+
+        assert_eq!(query.parents()[0].names().count(), 2);
+        assert_eq!(leaf_names, vec!["london"]);
+        assert_eq!(query.leaf().names.len(), 2);
+        assert!(query.leaf().names[1].excluded);
+    }
+
+    #[test]
+    fn keep_leading_caret_in_regex_mode() {
+        let query = prepare_query("^lond", MatchMode::Regex).unwrap();
+
+        assert!(!query.anchored);
+        assert_eq!(query.leaf().names().next().unwrap().text(), "^lond");
+        assert!(
+            prepare_query("^lond", MatchMode::Substring)
+                .unwrap()
+                .anchored
+        );
+    }
+
+    #[test]
+    fn parse_quoted_name_as_exact() {
+        let query = prepare_query("\"New > York\"", MatchMode::Substring).unwrap();
+        let name = query.leaf().names().next().unwrap();
+
+        assert_eq!(query.parts.len(), 1);
+        assert_eq!(name.text(), "new > york");
+        assert_eq!(name.mode(), MatchMode::Exact);
+    }
+
+    #[test]
+    fn parse_glob_and_like_patterns() {
+        let query = prepare_query("l?n*n", MatchMode::Glob).unwrap();
+        let name = query.leaf().names().next().unwrap();
+
+        assert!(name.matches_name("london"));
+        assert!(!name.matches_name("londonderry"));
+        assert_eq!(name.sql_pattern(), "l_n%n");
+
+        let query = prepare_query("100%_a", MatchMode::Substring).unwrap();
+
+        assert_eq!(
+            query.leaf().names().next().unwrap().sql_pattern(),
+            "%100\\%\\_a%"
+        );
+    }
+
+    #[test]
+    fn parse_suggest_query_with_prefix_leaf() {
+        let query = prepare_suggest_query("^brit.*n > \"England\" | lon(", MatchMode::Regex);
+        let query = query.unwrap();
+        let parent = query.parents()[0].names().next().unwrap();
+        let leaf_names: Vec<_> = query.leaf().names().map(|name| name.mode()).collect();
+
+        assert_eq!(parent.mode(), MatchMode::Regex);
+        assert!(parent.matches_name("britain"));
+        assert_eq!(leaf_names, vec![MatchMode::Exact, MatchMode::Prefix]);
+        assert_eq!(query.leaf().names().nth(1).unwrap().text(), "lon(");
+        assert_eq!(escape_like("100%_a\\"), "100\\%\\_a\\\\");
+    }
+
+    #[test]
+    fn parse_suggest_query_with_empty_leaf() {
+        let query = prepare_suggest_query("britain | scotland >> ", MatchMode::Exact).unwrap();
+
+        assert!(query.leaf().is_empty());
+        assert_eq!(query.parents().len(), 1);
+
+        let query = query.into_parent_query().unwrap();
+        let leaf_names: Vec<_> = query.leaf().names().map(QueryName::text).collect();
+
+        assert_eq!(leaf_names, vec!["britain", "scotland"]);
+        assert_eq!(
+            query.leaf().names().next().unwrap().mode(),
+            MatchMode::Exact
+        );
+        assert!(query.into_parent_query().is_none());
+        assert!(prepare_suggest_query(" > ", MatchMode::Substring).is_err());
+        assert!(prepare_suggest_query("britain | ", MatchMode::Substring).is_err());
+        assert!(prepare_query("britain > ", MatchMode::Substring).is_err());
+    }
+
+    #[test]
+    fn reject_invalid_queries() {
+        assert!(prepare_query("  ", MatchMode::Substring).is_err());
+        assert!(prepare_query("!london", MatchMode::Substring).is_err());
+        assert!(prepare_query("britain >> ***", MatchMode::Substring).is_err());
+        assert_eq!(
+            error_position("britain >> ", MatchMode::Substring),
+            Some(12)
+        );
+        assert_eq!(
+            error_position("britain || london", MatchMode::Substring),
+            Some(10)
+        );
+        assert_eq!(error_position("\"york", MatchMode::Substring), Some(1));
+        assert_eq!(error_position("york\" >> x", MatchMode::Substring), Some(5));
+        assert_eq!(error_position("britain >> (", MatchMode::Regex), Some(12));
+    }
+
+    #[test]
+    fn match_ancestors_and_direct_parents() {
+        let hierarchy = [Some(1), Some(2), Some(3)];
+
+        assert!(matches("london", &hierarchy));
+        assert!(matches("britain >> london", &hierarchy));
+        assert!(matches("britain >> england > london", &hierarchy));
+        assert!(matches("england > london", &hierarchy));
+        assert!(!matches("britain > london", &hierarchy));
+        assert!(!matches("london >> england", &hierarchy));
+        assert!(!matches("paris >> london", &hierarchy));
+    }
+
+    #[test]
+    fn match_anchored_query_from_top_level_region() {
+        let hierarchy = [Some(1), Some(2), Some(3)];
+
+        assert!(matches("^britain >> london", &hierarchy));
+        assert!(!matches("^england >> london", &hierarchy));
+    }
+
+    #[test]
+    fn match_anchored_single_part_query_at_top_level_only() {
+        assert!(matches("^britain", &[Some(1)]));
+        assert!(!matches("^london", &[Some(1), Some(2), Some(3)]));
+        assert!(matches("london", &[Some(1), Some(2), Some(3)]));
+    }
+
+    #[test]
+    fn match_alternatives_and_exclusions_of_parents() {
+        let hierarchy = [Some(1), Some(2), Some(3)];
+
+        assert!(matches("scotland|england > london", &hierarchy));
+        assert!(!matches("!england > london", &hierarchy));
+        assert!(matches("!scotland > london", &hierarchy));
+    }
+
+    #[test]
+    fn match_hierarchy_with_unloaded_parts() {
+        // Unknown part is accepted to avoid false negative result.
+        assert!(matches("england > london", &[Some(1), Some(99), Some(3)]));
+        assert!(!matches(
+            "england > london",
+            &[Some(1), Some(2), Some(4), Some(3)]
+        ));
+    }
+
+    #[test]
+    fn match_strict_hierarchy_with_unloaded_parts() {
+        let hierarchy = [Some(1), Some(99), Some(3)];
+
+        assert!(!matches_with("england > london", true, &hierarchy));
+        assert!(!matches_with("england >> london", true, &hierarchy));
+        assert!(matches_with("britain >> london", true, &hierarchy));
+        assert!(matches_with("london", true, &hierarchy));
+    }
+
+    #[test]
+    fn collect_missing_parts() {
+        let hierarchies = vec![
+            DbHierarchy::new(3, 3, &[Some(1), Some(99), Some(3)]),
+            DbHierarchy::new(5, 5, &[Some(98), Some(2), Some(99)]),
+        ];
+
+        assert_eq!(missing_parts(&hierarchies, &regions()), vec![5, 98, 99]);
+    }
+
+    fn explain(query: &str, parts: &[Option<i64>]) -> Result<Vec<PartMatch>, Rejection> {
+        let query = prepare_query(query, MatchMode::Substring).unwrap();
+
+        explain_hierarchy(&DbHierarchy::new(3, 3, parts), &query, &regions())
+    }
+
+    #[test]
+    fn explain_matched_parts() {
+        let matches = explain("tain >> eng|scot > lon", &[Some(1), Some(2), Some(3)]).unwrap();
+        let summary: Vec<_> = matches
+            .iter()
+            .map(|part_match| {
+                (
+                    part_match.part,
+                    part_match.region_id,
+                    part_match.name.as_deref(),
+                    part_match.language.as_deref(),
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            summary,
+            vec![
+                (0, 1, Some("Britain"), Some("en")),
+                (1, 2, Some("England"), Some("en")),
+                (2, 3, Some("London"), Some("en")),
+            ]
+        );
+
+        let matches = explain("wales >> england > london", &[Some(99), Some(2), Some(3)]).unwrap();
+
+        assert_eq!(matches[0].region_id, 99);
+        assert_eq!(matches[0].name, None);
+    }
+
+    #[test]
+    fn explain_rejected_hierarchy() {
+        let hierarchy = [Some(1), Some(2), Some(3)];
+
+        assert_eq!(
+            explain("scotland >> london", &hierarchy).unwrap_err(),
+            Rejection::Part(0)
+        );
+        assert_eq!(
+            explain("britain >> wales >> england > london", &hierarchy).unwrap_err(),
+            Rejection::Part(1)
+        );
+        assert_eq!(
+            explain("^england >> london", &hierarchy).unwrap_err(),
+            Rejection::Part(0)
+        );
+
+        let mut query = prepare_query("london", MatchMode::Substring).unwrap();
+
+        query
+            .set_filter(serde_json::from_str(r#"{ "maxDepth": 2 }"#).unwrap())
+            .unwrap();
+
+        assert_eq!(
+            explain_hierarchy(&DbHierarchy::new(3, 3, &hierarchy), &query, &regions()).unwrap_err(),
+            Rejection::Depth(3)
+        );
+    }
+
+    #[test]
+    fn match_hierarchy_without_leaf_part() {
+        let query = prepare_query("england >> camden", MatchMode::Substring).unwrap();
+
+        assert!(is_hierarchy_matches(
+            &DbHierarchy::new(4, 4, &[Some(1), Some(2)]),
+            &query,
+            &regions()
+        ));
+    }
+
+    #[test]
+    fn match_leaf_filter() {
+        let filter = |yaml: &str| -> LeafFilter { serde_json::from_str(yaml).unwrap() };
+        let hierarchy = DbHierarchy::new(3, 3, &[Some(1), Some(2), Some(3)]);
+        let check = |json: &str| {
+            let mut query = prepare_query("london", MatchMode::Substring).unwrap();
+
+            query.set_filter(filter(json)).unwrap();
+            is_hierarchy_matches(&hierarchy, &query, &regions())
+        };
+
+        assert!(check(r#"{ "minDepth": 3, "maxDepth": 3 }"#));
+        assert!(!check(r#"{ "minDepth": 4 }"#));
+        assert!(!check(r#"{ "maxDepth": 2 }"#));
+        assert!(check(r#"{ "types": ["town", "city"] }"#));
+        assert!(!check(r#"{ "types": ["district"] }"#));
+
+        let mut query = prepare_query("london", MatchMode::Substring).unwrap();
+
+        assert!(query.set_filter(filter(r#"{ "minDepth": 0 }"#)).is_err());
+        assert!(query
+            .set_filter(filter(r#"{ "minDepth": 3, "maxDepth": 2 }"#))
+            .is_err());
+    }
+}
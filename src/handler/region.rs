@@ -0,0 +1,82 @@
+use crate::cache::RegionCacheRef;
+use crate::config::ConfigRef;
+use crate::database::ClientPoolRef;
+use crate::handler::common;
+use crate::handler::common::Response;
+use crate::handler::error::HandlerError;
+use crate::handler::util::handle_request;
+use crate::index::RegionIndexRef;
+use crate::manager::DynamicConnectionsRef;
+use iron::middleware::Handler;
+use iron::IronResult;
+use iron::Request as IromRequest;
+use iron::Response as IromResponse;
+
+#[derive(Debug)]
+pub struct RegionHandler {
+    config: ConfigRef,
+    dynamic_connections: DynamicConnectionsRef,
+    region_cache: RegionCacheRef,
+    client_pool: ClientPoolRef,
+    region_index: RegionIndexRef,
+}
+
+impl RegionHandler {
+    pub fn new(
+        config: ConfigRef,
+        dynamic_connections: DynamicConnectionsRef,
+        region_cache: RegionCacheRef,
+        client_pool: ClientPoolRef,
+        region_index: RegionIndexRef,
+    ) -> RegionHandler {
+        RegionHandler {
+            config,
+            dynamic_connections,
+            region_cache,
+            client_pool,
+            region_index,
+        }
+    }
+}
+
+impl Handler for RegionHandler {
+    fn handle(&self, request: &mut IromRequest) -> IronResult<IromResponse> {
+        handle_request(request, move |request: Request| {
+            let mut source = common::region_source(
+                &self.config,
+                &self.dynamic_connections,
+                &self.region_cache,
+                &self.client_pool,
+                &self.region_index,
+                request.connection,
+            )?;
+
+            source.set_languages(&request.languages);
+
+            let regions = source
+                .regions_by_id(&[request.id])
+                .map_err(|error| HandlerError::database_error("Failed to query region", error))?;
+
+            if regions.is_empty() {
+                return Err(HandlerError::new(&format!(
+                    "Region with id `{}` not found",
+                    request.id
+                )));
+            }
+
+            let hierarchies = common::collect_hierarchy(source.as_mut(), vec![request.id])?;
+            let all_regions = common::collect_all_regions(source.as_mut(), &regions, &hierarchies)?;
+
+            Ok(Response::new(all_regions, &hierarchies))
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Request {
+    connection: usize,
+    id: i64,
+    #[serde(default)]
+    languages: Vec<String>,
+}
@@ -0,0 +1,193 @@
+use crate::cache::RegionCacheRef;
+use crate::config::ConfigRef;
+use crate::database::ClientPoolRef;
+use crate::database::Hierarchy as DbHierarchy;
+use crate::database::Region as DbRegion;
+use crate::handler::common;
+use crate::handler::error::HandlerError;
+use crate::handler::error::HandlerResult;
+use crate::handler::query;
+use crate::handler::query::MatchMode;
+use crate::handler::query::QueryName;
+use crate::handler::util::handle_request;
+use crate::manager::DynamicConnectionsRef;
+use iron::middleware::Handler;
+use iron::IronResult;
+use iron::Request as IromRequest;
+use iron::Response as IromResponse;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+#[derive(Debug)]
+pub struct SuggestHandler {
+    config: ConfigRef,
+    dynamic_connections: DynamicConnectionsRef,
+    region_cache: RegionCacheRef,
+    client_pool: ClientPoolRef,
+}
+
+const DEFAULT_LIMIT: usize = 10;
+const MAX_LIMIT: usize = 100;
+
+impl SuggestHandler {
+    pub fn new(
+        config: ConfigRef,
+        dynamic_connections: DynamicConnectionsRef,
+        region_cache: RegionCacheRef,
+        client_pool: ClientPoolRef,
+    ) -> SuggestHandler {
+        SuggestHandler {
+            config,
+            dynamic_connections,
+            region_cache,
+            client_pool,
+        }
+    }
+
+    fn prepare_limit(&self, limit: Option<usize>) -> HandlerResult<usize> {
+        match limit {
+            None => Ok(DEFAULT_LIMIT),
+            Some(limit) if limit > 0 && limit <= MAX_LIMIT => Ok(limit),
+            Some(limit) => Err(HandlerError::new(&format!(
+                "Suggestion limit must be in range [1, {}], but {} given",
+                MAX_LIMIT, limit
+            ))),
+        }
+    }
+
+    /// Suggests region names started with any of given prefixes, default names are suggested for
+    /// regions without such names.
+    fn collect_suggestions(
+        &self,
+        prefixes: &[&str],
+        regions: &HashMap<i64, DbRegion>,
+        region_ids: &HashSet<i64>,
+        limit: usize,
+    ) -> Vec<Suggestion> {
+        let mut suggestions: Vec<_> = region_ids
+            .iter()
+            .filter_map(|id| regions.get(id).map(|region| (id, region)))
+            .map(|(&id, region)| {
+                let name = region
+                    .names()
+                    .iter()
+                    .map(|name| name.name())
+                    .find(|name| {
+                        let name = name.to_lowercase();
+
+                        prefixes.iter().any(|prefix| name.starts_with(prefix))
+                    })
+                    .unwrap_or_else(|| region.default_name());
+
+                Suggestion::new(id, name)
+            })
+            .collect();
+
+        suggestions.sort_by(|a, b| a.name.cmp(&b.name).then(a.id.cmp(&b.id)));
+        suggestions.truncate(limit);
+        suggestions
+    }
+}
+
+impl Handler for SuggestHandler {
+    fn handle(&self, request: &mut IromRequest) -> IronResult<IromResponse> {
+        handle_request(request, move |request: Request| {
+            let limit = self.prepare_limit(request.limit)?;
+            let query = query::prepare_suggest_query(&request.query, request.match_mode)?;
+            let mut client = common::prepare_connection(
+                &self.config,
+                &self.dynamic_connections,
+                &self.region_cache,
+                &self.client_pool,
+                request.connection,
+            )?;
+
+            client.set_languages(&request.languages);
+
+            // Query ended with separator has empty leaf, direct children of regions matched to
+            // the last parent part are suggested.
+            if query.leaf().is_empty() {
+                let parent_query = query.into_parent_query().ok_or_else(|| {
+                    HandlerError::new("Region name must contain at least one not excluded name")
+                })?;
+                let parents: BTreeSet<_> =
+                    common::find_query_hierarchies(&mut client, &parent_query)?
+                        .into_hierarchies()
+                        .iter()
+                        .map(DbHierarchy::region_id)
+                        .collect();
+                let mut children = client.children_by_id(parents).map_err(|error| {
+                    HandlerError::database_error("Failed to query region children", error)
+                })?;
+
+                children.retain(|_, region| !region.names().is_empty());
+
+                let region_ids = children.keys().cloned().collect();
+
+                return Ok(self.collect_suggestions(&[], &children, &region_ids, limit));
+            }
+
+            let prefixes: Vec<_> = query.leaf().names().map(QueryName::text).collect();
+            let mut candidates = HashMap::new();
+
+            for prefix in &prefixes {
+                let pattern = query::escape_like(prefix);
+                let regions = client.regions_by_prefix(&pattern).map_err(|error| {
+                    HandlerError::database_error("Failed to query region by prefix", error)
+                })?;
+
+                candidates.extend(regions);
+            }
+
+            candidates.retain(|_, region| {
+                !region.names().is_empty() && !query.leaf().is_excluded(region)
+            });
+
+            // Without parent parts every candidate is acceptable, otherwise only candidates with
+            // at least one hierarchy matched to the resolved parent parts are kept.
+            if query.parents().is_empty() {
+                let region_ids = candidates.keys().cloned().collect();
+
+                return Ok(self.collect_suggestions(&prefixes, &candidates, &region_ids, limit));
+            }
+
+            let hierarchies = common::collect_hierarchy(&mut client, candidates.keys().cloned())?;
+            let all_regions = common::collect_all_regions(&mut client, &candidates, &hierarchies)?;
+            let region_ids = query::collect_query_hierarchies(&query, &all_regions, &hierarchies)
+                .into_iter()
+                .map(|hierarchy| hierarchy.id())
+                .collect();
+
+            Ok(self.collect_suggestions(&prefixes, &candidates, &region_ids, limit))
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Request {
+    connection: usize,
+    query: String,
+    limit: Option<usize>,
+    #[serde(default)]
+    languages: Vec<String>,
+    #[serde(default)]
+    match_mode: MatchMode,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Suggestion {
+    id: i64,
+    name: String,
+}
+
+impl Suggestion {
+    fn new(id: i64, name: &str) -> Suggestion {
+        Suggestion {
+            id,
+            name: name.into(),
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use crate::handler::error::HandlerError;
 use crate::handler::error::HandlerResult;
 use iron::mime::Mime;
 use iron::mime::SubLevel;
@@ -8,7 +9,6 @@ use iron::Request;
 use iron::Response;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
-use serde_json;
 use std::io::Read;
 
 pub fn handle_empty<Res, F>(callback: F) -> IronResult<Response>
@@ -18,7 +18,7 @@ where
 {
     let response = match callback() {
         Ok(response) => ErrorResponse::success(response),
-        Err(err) => ErrorResponse::error(&format!("{}", err)),
+        Err(err) => ErrorResponse::error(&err),
     };
 
     struct_to_response(&response)
@@ -36,7 +36,7 @@ where
     };
     let response = match result {
         Ok(response) => ErrorResponse::success(response),
-        Err(err) => ErrorResponse::error(&format!("{}", err)),
+        Err(err) => ErrorResponse::error(&err),
     };
 
     struct_to_response(&response)
@@ -63,6 +63,10 @@ struct ErrorResponse<T> {
     result: Option<T>,
     #[serde(skip_serializing_if = "Option::is_none")]
     message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    position: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timeout: Option<bool>,
 }
 
 impl<T> ErrorResponse<T>
@@ -74,14 +78,18 @@ where
             success: true,
             result: Some(result),
             message: None,
+            position: None,
+            timeout: None,
         }
     }
 
-    fn error(message: &str) -> ErrorResponse<T> {
+    fn error(error: &HandlerError) -> ErrorResponse<T> {
         ErrorResponse {
             success: false,
             result: None,
-            message: Some(message.into()),
+            message: Some(format!("{}", error)),
+            position: error.position(),
+            timeout: Some(error.is_timeout()).filter(|&timeout| timeout),
         }
     }
 }
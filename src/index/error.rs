@@ -0,0 +1,58 @@
+use crate::database::DatabaseError;
+use std::error::Error;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::io::Error as IoError;
+
+pub type RegionIndexResult<T> = Result<T, RegionIndexError>;
+
+#[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
+pub enum RegionIndexError {
+    StartThreadError { error: IoError },
+    QuerySchemaError { name: String },
+    DatabaseError { error: DatabaseError },
+}
+
+macro_rules! warn_error {
+    ($error:expr) => {{
+        let error = $error;
+
+        warn!("{}", error);
+
+        error
+    }};
+}
+
+impl RegionIndexError {
+    pub fn start_thread_error(error: IoError) -> RegionIndexError {
+        warn_error!(RegionIndexError::StartThreadError { error })
+    }
+
+    pub fn query_schema_error(name: &str) -> RegionIndexError {
+        warn_error!(RegionIndexError::QuerySchemaError { name: name.into() })
+    }
+
+    pub fn database_error(error: DatabaseError) -> RegionIndexError {
+        warn_error!(RegionIndexError::DatabaseError { error })
+    }
+}
+
+impl Error for RegionIndexError {}
+
+impl Display for RegionIndexError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            RegionIndexError::StartThreadError { error } => {
+                write!(f, "Failed to start thread - {}", error)
+            }
+            RegionIndexError::QuerySchemaError { name } => {
+                write!(f, "Query schema `{}` is not defined", name)
+            }
+            RegionIndexError::DatabaseError { error } => {
+                write!(f, "Failed to load regions - {}", error)
+            }
+        }
+    }
+}
@@ -0,0 +1,429 @@
+mod error;
+
+pub use self::error::RegionIndexError;
+pub use self::error::RegionIndexResult;
+
+use crate::cache::RegionCacheRef;
+use crate::config::ConfigRef;
+use crate::config::ConnectionKind;
+use crate::config::ConnectionSettings;
+use crate::config::QuerySchemaSettings;
+use crate::database;
+use crate::database::ClientPoolRef;
+use crate::database::Database;
+use crate::database::DatabaseError;
+use crate::database::DatabaseResult;
+use crate::database::Hierarchy;
+use crate::database::LanguageFilter;
+use crate::database::Region;
+use crate::database::RegionRecord;
+use crate::database::RegionSource;
+use regex::Regex;
+use regex::RegexBuilder;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::RwLock;
+use std::thread;
+use std::thread::Builder;
+use std::time::Duration;
+
+type Trigram = [char; 3];
+
+/// All region names and hierarchies of single connection. Lower case names are indexed by
+/// trigrams, so names containing given literal text are found without full scan.
+#[derive(Debug)]
+pub struct RegionIndex {
+    records: Vec<RegionRecord>,
+    lower_names: Vec<String>,
+    trigrams: HashMap<Trigram, Vec<usize>>,
+    region_records: HashMap<i64, Vec<usize>>,
+    hierarchies: HashMap<i64, Vec<Hierarchy>>,
+}
+
+impl RegionIndex {
+    fn new(records: Vec<RegionRecord>, hierarchies: Vec<Hierarchy>) -> RegionIndex {
+        let lower_names: Vec<_> = records
+            .iter()
+            .map(|record| record.name().to_lowercase())
+            .collect();
+        let mut trigrams: HashMap<_, Vec<_>> = HashMap::new();
+        let mut region_records: HashMap<_, Vec<_>> = HashMap::new();
+
+        for (position, name) in lower_names.iter().enumerate() {
+            for trigram in name_trigrams(name) {
+                let positions = trigrams.entry(trigram).or_default();
+
+                if positions.last() != Some(&position) {
+                    positions.push(position);
+                }
+            }
+        }
+
+        for (position, record) in records.iter().enumerate() {
+            region_records
+                .entry(record.id())
+                .or_default()
+                .push(position);
+        }
+
+        let mut hierarchies_by_id: HashMap<_, Vec<_>> = HashMap::new();
+
+        for hierarchy in hierarchies {
+            hierarchies_by_id
+                .entry(hierarchy.id())
+                .or_default()
+                .push(hierarchy);
+        }
+
+        RegionIndex {
+            records,
+            lower_names,
+            trigrams,
+            region_records,
+            hierarchies: hierarchies_by_id,
+        }
+    }
+
+    /// Returns positions of names satisfying given predicate. Every matched name must contain all
+    /// given literals, they are used to select candidate names.
+    pub fn find<P>(&self, literals: &[String], predicate: P) -> BTreeSet<usize>
+    where
+        P: Fn(&str) -> bool,
+    {
+        let mut candidates: Option<&Vec<usize>> = None;
+
+        for trigram in literals.iter().flat_map(|literal| name_trigrams(literal)) {
+            match self.trigrams.get(&trigram) {
+                Some(positions) if candidates.is_none_or(|c| positions.len() < c.len()) => {
+                    candidates = Some(positions)
+                }
+                Some(_) => {}
+                None => return BTreeSet::new(),
+            }
+        }
+
+        match candidates {
+            Some(positions) => positions
+                .iter()
+                .cloned()
+                .filter(|&position| predicate(&self.lower_names[position]))
+                .collect(),
+            None => (0..self.lower_names.len())
+                .filter(|&position| predicate(&self.lower_names[position]))
+                .collect(),
+        }
+    }
+
+    pub fn records<'a, I>(&'a self, positions: I) -> impl Iterator<Item = &'a RegionRecord>
+    where
+        I: IntoIterator<Item = usize> + 'a,
+    {
+        positions
+            .into_iter()
+            .map(move |position| &self.records[position])
+    }
+
+    /// Returns all names of given regions.
+    pub fn region_records<'a, I>(&'a self, ids: I) -> impl Iterator<Item = &'a RegionRecord>
+    where
+        I: IntoIterator<Item = i64> + 'a,
+    {
+        ids.into_iter()
+            .filter_map(move |id| self.region_records.get(&id))
+            .flatten()
+            .map(move |&position| &self.records[position])
+    }
+
+    pub fn hierarchy_by_id<I>(&self, ids: I) -> Vec<Hierarchy>
+    where
+        I: IntoIterator<Item = i64>,
+    {
+        ids.into_iter()
+            .filter_map(|id| self.hierarchies.get(&id))
+            .flatten()
+            .cloned()
+            .collect()
+    }
+}
+
+/// Region source answering lookups from loaded region index.
+pub struct IndexSource<'a> {
+    region_index: Arc<RegionIndex>,
+    query_schema: &'a QuerySchemaSettings,
+    filter: LanguageFilter,
+}
+
+impl<'a> IndexSource<'a> {
+    pub fn new(
+        region_index: Arc<RegionIndex>,
+        query_schema: &QuerySchemaSettings,
+    ) -> IndexSource<'_> {
+        IndexSource {
+            region_index,
+            query_schema,
+            filter: LanguageFilter::new(&[], query_schema),
+        }
+    }
+
+    /// Returns regions with any name satisfying given predicate. Regions are built from all their
+    /// names, not only from matched ones.
+    fn find_regions<P>(&self, literals: &[String], predicate: P) -> HashMap<i64, Region>
+    where
+        P: Fn(&str) -> bool,
+    {
+        let positions = self.region_index.find(literals, predicate);
+        let ids: BTreeSet<_> = self
+            .region_index
+            .records(positions)
+            .map(RegionRecord::id)
+            .collect();
+
+        self.filter
+            .build_regions(self.region_index.region_records(ids))
+    }
+}
+
+impl RegionSource for IndexSource<'_> {
+    fn set_languages(&mut self, languages: &[String]) {
+        self.filter = LanguageFilter::new(languages, self.query_schema);
+    }
+
+    fn has_region_types(&self) -> bool {
+        self.filter.has_region_types()
+    }
+
+    fn regions_by_name(&mut self, name: &str) -> DatabaseResult<HashMap<i64, Region>> {
+        let (regex, literals) = like_to_regex(name)?;
+
+        Ok(self.find_regions(&literals, |text| regex.is_match(text)))
+    }
+
+    fn regions_by_regex(&mut self, pattern: &str) -> DatabaseResult<HashMap<i64, Region>> {
+        let regex = RegexBuilder::new(pattern)
+            .case_insensitive(true)
+            .build()
+            .map_err(DatabaseError::pattern_error)?;
+
+        Ok(self.find_regions(&[], |text| regex.is_match(text)))
+    }
+
+    fn regions_by_id(&mut self, ids: &[i64]) -> DatabaseResult<HashMap<i64, Region>> {
+        Ok(self
+            .filter
+            .build_regions(self.region_index.region_records(ids.iter().cloned())))
+    }
+
+    fn hierarchy_by_id(&mut self, ids: &[i64]) -> DatabaseResult<Vec<Hierarchy>> {
+        Ok(self.region_index.hierarchy_by_id(ids.iter().cloned()))
+    }
+}
+
+/// Converts case insensitive SQL `like` pattern to regular expression matching lower case names.
+/// Returns literal parts of pattern too.
+fn like_to_regex(pattern: &str) -> DatabaseResult<(Regex, Vec<String>)> {
+    let mut regex = String::from("^");
+    let mut literals = Vec::new();
+    let mut literal = String::new();
+    let mut chars = pattern.chars();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '%' | '_' => {
+                if !literal.is_empty() {
+                    regex.push_str(&regex::escape(&literal));
+                    literals.push(std::mem::take(&mut literal));
+                }
+
+                regex.push_str(if ch == '%' { ".*" } else { "." });
+            }
+            '\\' => literal.extend(
+                chars
+                    .next()
+                    .map(|ch| ch.to_lowercase())
+                    .into_iter()
+                    .flatten(),
+            ),
+            _ => literal.extend(ch.to_lowercase()),
+        }
+    }
+
+    if !literal.is_empty() {
+        regex.push_str(&regex::escape(&literal));
+        literals.push(literal);
+    }
+
+    regex.push('$');
+
+    let regex = Regex::new(&regex).map_err(DatabaseError::pattern_error)?;
+
+    Ok((regex, literals))
+}
+
+fn name_trigrams(name: &str) -> Vec<Trigram> {
+    let chars: Vec<_> = name.chars().collect();
+
+    chars
+        .windows(3)
+        .map(|window| [window[0], window[1], window[2]])
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+pub struct RegionIndexRef {
+    inner: Arc<RwLock<HashMap<usize, Arc<RegionIndex>>>>,
+}
+
+impl RegionIndexRef {
+    /// Returns index of connection with given index if it was loaded.
+    pub fn get(&self, index: usize) -> Option<Arc<RegionIndex>> {
+        match self.inner.read() {
+            Ok(indices) => indices.get(&index).cloned(),
+            Err(err) => {
+                warn!("Failed to acquire read lock - {}", err);
+
+                None
+            }
+        }
+    }
+
+    fn set(&self, index: usize, region_index: RegionIndex) {
+        match self.inner.write() {
+            Ok(mut indices) => {
+                indices.insert(index, Arc::new(region_index));
+            }
+            Err(err) => warn!("Failed to acquire write lock - {}", err),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct PreloadWorker {
+    index: usize,
+    interval: Duration,
+    connection: ConnectionSettings,
+    config: ConfigRef,
+    region_cache: RegionCacheRef,
+    client_pool: ClientPoolRef,
+    region_index: RegionIndexRef,
+}
+
+impl PreloadWorker {
+    /// Loads index every interval. If index is already loaded, the first load is delayed.
+    fn run(self, loaded: bool) {
+        info!(
+            "Preload thread started: connection = {}",
+            self.connection.description()
+        );
+
+        if loaded {
+            thread::sleep(self.interval);
+        }
+
+        loop {
+            match self.load() {
+                Ok(region_index) => self.region_index.set(self.index, region_index),
+                Err(error) => warn!(
+                    "Failed to preload connection {} - {}",
+                    self.connection.description(),
+                    error
+                ),
+            }
+
+            thread::sleep(self.interval);
+        }
+    }
+
+    fn load(&self) -> RegionIndexResult<RegionIndex> {
+        let (records, hierarchies) = match self.connection.kind() {
+            ConnectionKind::File => database::load_dataset(&self.connection)
+                .map_err(RegionIndexError::database_error)?,
+            _ => self.load_database()?,
+        };
+
+        info!(
+            "Region index loaded: connection = {}, names = {}, hierarchies = {}",
+            self.connection.description(),
+            records.len(),
+            hierarchies.len()
+        );
+
+        Ok(RegionIndex::new(records, hierarchies))
+    }
+
+    fn load_database(&self) -> RegionIndexResult<(Vec<RegionRecord>, Vec<Hierarchy>)> {
+        let query_schema = self
+            .config
+            .query_schemas()
+            .get(self.connection.query_schema())
+            .ok_or_else(|| RegionIndexError::query_schema_error(self.connection.query_schema()))?;
+        let mut client = Database::new(
+            self.connection.clone(),
+            query_schema,
+            self.region_cache.clone(),
+            self.client_pool.clone(),
+        )
+        .connect()
+        .map_err(RegionIndexError::database_error)?;
+
+        // Bulk load queries are expected to be slow, statement timeout applies to searches only.
+        client.set_statement_timeout(None);
+
+        let records = client
+            .all_regions()
+            .map_err(RegionIndexError::database_error)?;
+        let hierarchies = client
+            .all_hierarchies()
+            .map_err(RegionIndexError::database_error)?;
+
+        Ok((records, hierarchies))
+    }
+}
+
+pub fn region_index() -> RegionIndexRef {
+    RegionIndexRef {
+        inner: Arc::new(RwLock::new(HashMap::new())),
+    }
+}
+
+/// Starts preload thread for every static connection with `preload` settings. File connections
+/// are loaded before start, they are reloaded only if `preload` is set.
+pub fn start(
+    config: &ConfigRef,
+    region_cache: &RegionCacheRef,
+    client_pool: &ClientPoolRef,
+    region_index: &RegionIndexRef,
+) -> RegionIndexResult<()> {
+    for (index, connection) in config.connections().static_connections().iter().enumerate() {
+        let is_file = connection.kind() == ConnectionKind::File;
+
+        if connection.preload().is_none() && !is_file {
+            continue;
+        }
+
+        let worker = PreloadWorker {
+            index,
+            interval: Duration::from_secs(
+                connection.preload().map_or(0, |preload| preload.interval()),
+            ),
+            connection: connection.clone(),
+            config: config.clone(),
+            region_cache: region_cache.clone(),
+            client_pool: client_pool.clone(),
+            region_index: region_index.clone(),
+        };
+
+        if is_file {
+            region_index.set(index, worker.load()?);
+        }
+
+        if connection.preload().is_some() {
+            Builder::new()
+                .name(format!("preload connection {}", index))
+                .spawn(move || worker.run(is_file))
+                .map_err(RegionIndexError::start_thread_error)?;
+        }
+    }
+
+    Ok(())
+}
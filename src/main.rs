@@ -3,10 +3,12 @@ extern crate log;
 #[macro_use]
 extern crate serde_derive;
 
+mod cache;
 mod config;
 mod database;
 mod error;
 mod handler;
+mod index;
 mod manager;
 mod options;
 mod server;
@@ -27,11 +29,29 @@ fn main() -> ApplicationResult {
     config::validate(&config).map_err(ApplicationError::config_error)?;
 
     let dynamic_connections = manager::dynamic_connections(&config);
+    let region_cache = cache::region_cache(&config);
+    let client_pool = database::client_pool();
+    let region_index = index::region_index();
 
     if let Some(settings) = config.connections().dynamic_connections() {
-        worker::start(settings, dynamic_connections.clone())
-            .map_err(ApplicationError::update_connections_error)?;
+        worker::start(
+            settings,
+            dynamic_connections.clone(),
+            region_cache.clone(),
+            client_pool.clone(),
+        )
+        .map_err(ApplicationError::update_connections_error)?;
     }
 
-    server::start(&options, config, dynamic_connections)
+    index::start(&config, &region_cache, &client_pool, &region_index)
+        .map_err(ApplicationError::preload_error)?;
+
+    server::start(
+        &options,
+        config,
+        dynamic_connections,
+        region_cache,
+        client_pool,
+        region_index,
+    )
 }
@@ -1,8 +1,18 @@
+use crate::cache::RegionCacheRef;
 use crate::config::ConfigRef;
+use crate::database::ClientPoolRef;
 use crate::error::ApplicationError;
 use crate::error::ApplicationResult;
+use crate::handler::CacheHandler;
+use crate::handler::ChildrenHandler;
 use crate::handler::ConnectionsHandler;
+use crate::handler::DiffHandler;
+use crate::handler::FindRegionByPointHandler;
 use crate::handler::FindRegionHandler;
+use crate::handler::LintHandler;
+use crate::handler::RegionHandler;
+use crate::handler::SuggestHandler;
+use crate::index::RegionIndexRef;
 use crate::manager::DynamicConnectionsRef;
 use crate::options::Options;
 use iron::Iron;
@@ -13,6 +23,9 @@ pub fn start(
     options: &Options,
     config: ConfigRef,
     dynamic_connections: DynamicConnectionsRef,
+    region_cache: RegionCacheRef,
+    client_pool: ClientPoolRef,
+    region_index: RegionIndexRef,
 ) -> ApplicationResult {
     let mut mount = Mount::new();
     mount.mount(
@@ -21,8 +34,71 @@ pub fn start(
     );
     mount.mount(
         "/api/v1/find_region",
-        FindRegionHandler::new(config, dynamic_connections),
+        FindRegionHandler::new(
+            config.clone(),
+            dynamic_connections.clone(),
+            region_cache.clone(),
+            client_pool.clone(),
+            region_index.clone(),
+        ),
     );
+    mount.mount(
+        "/api/v1/find_region_by_point",
+        FindRegionByPointHandler::new(
+            config.clone(),
+            dynamic_connections.clone(),
+            region_cache.clone(),
+            client_pool.clone(),
+        ),
+    );
+    mount.mount(
+        "/api/v1/region",
+        RegionHandler::new(
+            config.clone(),
+            dynamic_connections.clone(),
+            region_cache.clone(),
+            client_pool.clone(),
+            region_index.clone(),
+        ),
+    );
+    mount.mount(
+        "/api/v1/children",
+        ChildrenHandler::new(
+            config.clone(),
+            dynamic_connections.clone(),
+            region_cache.clone(),
+            client_pool.clone(),
+        ),
+    );
+    mount.mount(
+        "/api/v1/suggest",
+        SuggestHandler::new(
+            config.clone(),
+            dynamic_connections.clone(),
+            region_cache.clone(),
+            client_pool.clone(),
+        ),
+    );
+    mount.mount(
+        "/api/v1/diff",
+        DiffHandler::new(
+            config.clone(),
+            dynamic_connections.clone(),
+            region_cache.clone(),
+            client_pool.clone(),
+            region_index,
+        ),
+    );
+    mount.mount(
+        "/api/v1/lint",
+        LintHandler::new(
+            config,
+            dynamic_connections,
+            region_cache.clone(),
+            client_pool,
+        ),
+    );
+    mount.mount("/api/v1/cache", CacheHandler::new(region_cache));
     mount.mount("/static", Static::new("public/static"));
     mount.mount("/", Static::new("public"));
 
@@ -8,6 +8,7 @@ use std::io::Error as IoError;
 pub type UpdateConnectionsResult<T> = Result<T, UpdateConnectionsError>;
 
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum UpdateConnectionsError {
     StartThreadError { error: IoError },
     SpawnCommandError { error: IoError },
@@ -43,6 +44,7 @@ impl UpdateConnectionsError {
         warn_error!(UpdateConnectionsError::WaitCommandError { error })
     }
 
+    #[allow(clippy::self_named_constructors)]
     pub fn update_connections_error(error: DynamicConnectionsError) -> UpdateConnectionsError {
         warn_error!(UpdateConnectionsError::UpdateConnectionsError { error })
     }
@@ -3,9 +3,12 @@ mod error;
 pub use self::error::UpdateConnectionsError;
 pub use self::error::UpdateConnectionsResult;
 
+use crate::cache;
+use crate::cache::RegionCacheRef;
 use crate::config;
 use crate::config::ConnectionSettings;
 use crate::config::DynamicConnectionsSettings;
+use crate::database::ClientPoolRef;
 use crate::manager::DynamicConnectionsRef;
 use std::collections::HashMap;
 use std::io::BufRead;
@@ -22,17 +25,23 @@ pub struct UpdateConnectionsWorker {
     interval: Duration,
     command: String,
     dynamic_connections: DynamicConnectionsRef,
+    region_cache: RegionCacheRef,
+    client_pool: ClientPoolRef,
 }
 
 impl UpdateConnectionsWorker {
     fn new(
         config: &DynamicConnectionsSettings,
         dynamic_connections: DynamicConnectionsRef,
+        region_cache: RegionCacheRef,
+        client_pool: ClientPoolRef,
     ) -> UpdateConnectionsWorker {
         UpdateConnectionsWorker {
             interval: Duration::from_secs(config.interval()),
             command: config.command().into(),
             dynamic_connections,
+            region_cache,
+            client_pool,
         }
     }
 
@@ -80,7 +89,10 @@ impl UpdateConnectionsWorker {
 
         self.dynamic_connections
             .for_each(|index, connection| {
-                current_connections.insert(self.connection_string(connection), index);
+                current_connections.insert(
+                    self.connection_string(connection),
+                    (index, connection.clone()),
+                );
             })
             .map_err(UpdateConnectionsError::update_connections_error)?;
 
@@ -89,7 +101,11 @@ impl UpdateConnectionsWorker {
         for connection in connections {
             let string = self.connection_string(&connection);
 
-            if let Some(index) = current_connections.remove(&string) {
+            if let Some((index, current_connection)) = current_connections.remove(&string) {
+                if current_connection != connection {
+                    self.invalidate(&current_connection);
+                }
+
                 self.dynamic_connections
                     .update(index, connection)
                     .map_err(UpdateConnectionsError::update_connections_error)?;
@@ -100,15 +116,24 @@ impl UpdateConnectionsWorker {
             }
         }
 
-        for &index in current_connections.values() {
+        for (index, connection) in current_connections.values() {
+            self.invalidate(connection);
             self.dynamic_connections
-                .remove(index)
+                .remove(*index)
                 .map_err(UpdateConnectionsError::update_connections_error)?;
         }
 
         Ok(())
     }
 
+    /// Drops cached regions and idle database connections of changed or removed connection.
+    fn invalidate(&self, connection: &ConnectionSettings) {
+        let connection_key = cache::connection_key(connection);
+
+        self.region_cache.invalidate(&connection_key);
+        self.client_pool.invalidate(&connection_key);
+    }
+
     fn connection_string(&self, connection: &ConnectionSettings) -> String {
         match (
             connection.role(),
@@ -136,16 +161,14 @@ impl UpdateConnectionsWorker {
             let mut parts = line.splitn(2, ':').map(|value| value.trim());
 
             match (parts.next(), parts.next()) {
-                (Some(key), Some(value)) if key == "description" => builder.set_description(value),
-                (Some(key), Some(value)) if key == "query schema" => {
-                    builder.set_query_schema(value)
-                }
-                (Some(key), Some(value)) if key == "host" => builder.set_host(value),
-                (Some(key), Some(value)) if key == "port" => builder.set_port(value),
-                (Some(key), Some(value)) if key == "database" => builder.set_database(value),
-                (Some(key), Some(value)) if key == "role" => builder.set_role(value),
-                (Some(key), Some(value)) if key == "password" => builder.set_password(value),
-                (Some(key), _) if key == "~~~" => {
+                (Some("description"), Some(value)) => builder.set_description(value),
+                (Some("query schema"), Some(value)) => builder.set_query_schema(value),
+                (Some("host"), Some(value)) => builder.set_host(value),
+                (Some("port"), Some(value)) => builder.set_port(value),
+                (Some("database"), Some(value)) => builder.set_database(value),
+                (Some("role"), Some(value)) => builder.set_role(value),
+                (Some("password"), Some(value)) => builder.set_password(value),
+                (Some("~~~"), _) => {
                     if let Some(connection) = builder.build() {
                         connections.push(connection);
                     }
@@ -270,8 +293,11 @@ impl ConnectionBuilder {
 pub fn start(
     config: &DynamicConnectionsSettings,
     dynamic_connections: DynamicConnectionsRef,
+    region_cache: RegionCacheRef,
+    client_pool: ClientPoolRef,
 ) -> UpdateConnectionsResult<()> {
-    let worker = UpdateConnectionsWorker::new(config, dynamic_connections);
+    let worker =
+        UpdateConnectionsWorker::new(config, dynamic_connections, region_cache, client_pool);
 
     Builder::new()
         .name("dynamic connections updater".into())
@@ -280,3 +306,92 @@ pub fn start(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::database;
+    use crate::manager;
+
+    const CONFIG: &str = r#"
+connections:
+  dynamic_connections:
+    interval: 60
+    command: /bin/true
+  static_connections: []
+query_schemas: {}
+"#;
+
+    fn worker() -> UpdateConnectionsWorker {
+        let config: Config = serde_yaml::from_str(CONFIG).unwrap();
+
+        UpdateConnectionsWorker::new(
+            config.connections().dynamic_connections().unwrap(),
+            manager::dynamic_connections(&config),
+            cache::region_cache(&config),
+            database::client_pool(),
+        )
+    }
+
+    fn read(output: &str) -> Vec<ConnectionSettings> {
+        worker().read_connections(output.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn read_complete_connections() {
+        let connections = read(
+            "description: First\nquery schema: osm\nhost: db1\nport: 5433\n\
+             database: regions\nrole: reader\npassword: secret\n~~~\n\
+             description: Second\nquery schema: osm\nhost: db2\n\
+             database: regions\nrole: reader\n~~~\n",
+        );
+
+        assert_eq!(connections.len(), 2);
+        assert_eq!(connections[0].description(), "First");
+        assert_eq!(connections[0].query_schema(), "osm");
+        assert_eq!(connections[0].host(), "db1");
+        assert_eq!(connections[0].port(), Some(5433));
+        assert_eq!(connections[0].database(), "regions");
+        assert_eq!(connections[0].role(), "reader");
+        assert_eq!(
+            connections[0].password().map(String::as_str),
+            Some("secret")
+        );
+        assert_eq!(connections[1].port(), None);
+        assert_eq!(connections[1].password(), None);
+    }
+
+    #[test]
+    fn skip_connections_with_missing_fields() {
+        let connections = read(
+            "description: First\nquery schema: osm\nhost: db1\ndatabase: regions\n~~~\n\
+             description: Second\nquery schema: osm\nhost: db2\n\
+             database: regions\nrole: reader\n~~~\n",
+        );
+
+        assert_eq!(connections.len(), 1);
+        assert_eq!(connections[0].description(), "Second");
+    }
+
+    #[test]
+    fn ignore_invalid_port_and_keys() {
+        let connections = read(
+            "description: First\nquery schema: osm\nhost: db1\nport: many\n\
+             database: regions\nrole: reader\ncolor: red\n\n~~~\n",
+        );
+
+        assert_eq!(connections.len(), 1);
+        assert_eq!(connections[0].port(), None);
+    }
+
+    #[test]
+    fn skip_unterminated_connection() {
+        let connections = read(
+            "description: First\nquery schema: osm\nhost: db1\n\
+             database: regions\nrole: reader\n",
+        );
+
+        assert!(connections.is_empty());
+    }
+}